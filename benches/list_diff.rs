@@ -0,0 +1,45 @@
+//! `cargo bench` for diffing `_NET_CLIENT_LIST`
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+
+#[path = "../src/diff.rs"]
+#[cfg_attr(
+	test,
+	expect(
+		dead_code,
+		unused_imports,
+		reason = "its tests only run as part of the binary"
+	)
+)]
+mod diff;
+
+fn list_diff(c: &mut Criterion) {
+	let mut group = c.benchmark_group("list_diff");
+	for size in [10_u32, 100, 1000] {
+		let old = (0..size).collect::<Vec<_>>();
+		// a new window was mapped
+		let appended = (0..=size).collect::<Vec<_>>();
+		// a window in the middle was unmapped
+		let removed = (0..size).filter(|&val| val != size / 2).collect::<Vec<_>>();
+		// window manager reordered the list, e.g. stacking order
+		let reversed = (0..size).rev().collect::<Vec<_>>();
+		for (name, new) in [
+			("appended", &appended),
+			("removed", &removed),
+			("reversed", &reversed),
+		] {
+			group.bench_with_input(BenchmarkId::new(name, size), new, |b, new| {
+				b.iter_batched_ref(
+					|| old.clone(),
+					|target| diff::list_diff(target, black_box(new)),
+					criterion::BatchSize::SmallInput,
+				);
+			});
+		}
+	}
+	group.finish();
+}
+
+criterion_group!(benches, list_diff);
+criterion_main!(benches);
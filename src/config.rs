@@ -0,0 +1,323 @@
+//! everything read from the environment
+use std::env::var_os;
+use std::os::unix::ffi::OsStringExt;
+use std::rc::Rc;
+use std::str::from_utf8;
+
+use foldhash::HashMap;
+
+use crate::context::{HideStrategy, Inherit, Layout, Placement, WindowState};
+use crate::output;
+
+// TODO: replace with a real configuration file
+pub fn env_bytes(name: &str) -> Vec<u8> {
+	// since reading the /proc/{pid}/status returns arbitrary bytes,
+	// i need to support more than utf-8, so i use the unix strings
+	// [porting] figure out how your platform does process names
+	var_os(name).unwrap_or_default().into_vec()
+}
+pub fn env_iter(text: &[u8]) -> impl Iterator<Item = Rc<[u8]>> + '_ {
+	let iter = text.split(|&c| c == b':').map(|item| item.to_vec().into());
+	(!text.is_empty()).then_some(iter).into_iter().flatten()
+}
+
+/// `1` or `0`, for things that are off by default
+pub fn env_flag(name: &str) -> bool {
+	match env_bytes(name).as_slice() {
+		b"" | b"0" => false,
+		b"1" => true,
+		other => {
+			output::bad_config(name, other);
+			false
+		}
+	}
+}
+
+/// what to do with the children of a parent that exited while swallowed
+#[derive(Clone, Copy)]
+pub enum OrphanPolicy {
+	/// stop tracking them, they stay open
+	Leave,
+	/// ask them to close as well
+	Close,
+}
+
+impl OrphanPolicy {
+	pub fn from_env(name: &str) -> Self {
+		match env_bytes(name).as_slice() {
+			b"" | b"leave" => Self::Leave,
+			b"close" => Self::Close,
+			other => {
+				output::bad_config(name, other);
+				Self::Leave
+			}
+		}
+	}
+}
+
+/// when the parent gets focused after it's revealed
+#[derive(Clone, Copy)]
+pub enum FocusPolicy {
+	Always,
+	/// only if the child had the focus, so nothing gets stolen
+	IfFocused,
+	Never,
+}
+
+/// what happens when a hidden parent wants attention (e.g. a terminal bell)
+#[derive(Clone, Copy)]
+pub enum UrgencyPolicy {
+	Ignore,
+	/// make the children want attention too
+	Mirror,
+	/// stop swallowing, so the parent can be seen
+	Reveal,
+}
+
+/// when a parent comes back, if it swallowed more than one window
+#[derive(Clone, Copy)]
+pub enum VomitCondition {
+	/// once every window it swallowed is closed
+	Last,
+	/// once the window that swallowed it first is closed, the others stay
+	Primary,
+	/// once that window's process exits, even if it's left other windows or
+	/// none at all
+	Exit,
+}
+
+/// which window gets hidden when the parent process has several
+#[derive(Clone, Copy)]
+pub enum WindowChoice {
+	Oldest,
+	Newest,
+	/// highest in the stacking order, usually the one that was just used
+	Topmost,
+}
+
+/// settings that depend on which program the child is
+/// (or the parent, for `windows`)
+#[derive(Clone, Copy)]
+pub struct Rule {
+	pub hide: HideStrategy,
+	/// what the child takes from the parent when it first swallows it
+	pub swallow: Inherit,
+	/// what the parent takes from the child when it's revealed again,
+	/// on top of where it was before being hidden
+	pub vomit: Inherit,
+	pub focus: FocusPolicy,
+	pub urgency: UrgencyPolicy,
+	pub windows: WindowChoice,
+	/// from the first child, for all the others of the same parent
+	pub layout: Layout,
+	/// also from the first child
+	pub vomit_on: VomitCondition,
+}
+
+impl Default for Rule {
+	fn default() -> Self {
+		Self {
+			hide: HideStrategy::Unmap,
+			swallow: Inherit::ALL,
+			vomit: Inherit::ALL,
+			focus: FocusPolicy::IfFocused,
+			urgency: UrgencyPolicy::Mirror,
+			windows: WindowChoice::Oldest,
+			layout: Layout::None,
+			vomit_on: VomitCondition::Last,
+		}
+	}
+}
+
+impl Rule {
+	/// apply one `key=value` setting, false if it didn't make sense
+	fn set(&mut self, key: &[u8], value: &[u8]) -> bool {
+		match (key, value) {
+			(b"hide", b"unmap") => self.hide = HideStrategy::Unmap,
+			(b"hide", b"iconify") => self.hide = HideStrategy::Iconify,
+			(b"hide", b"offscreen") => self.hide = HideStrategy::Offscreen,
+			// hiding on a desktop needs to know which one
+			(b"desktop", _) => {
+				// being on every desktop wouldn't hide anything
+				let Some(desktop) = from_utf8(value)
+					.ok()
+					.and_then(|text| text.parse().ok())
+					.filter(|&desktop| desktop != u32::MAX)
+				else {
+					return false;
+				};
+				self.hide = HideStrategy::Desktop(desktop);
+			}
+			(b"focus", b"always") => self.focus = FocusPolicy::Always,
+			(b"focus", b"if-focused") => self.focus = FocusPolicy::IfFocused,
+			(b"focus", b"never") => self.focus = FocusPolicy::Never,
+			(b"urgency", b"ignore") => self.urgency = UrgencyPolicy::Ignore,
+			(b"urgency", b"mirror") => self.urgency = UrgencyPolicy::Mirror,
+			(b"urgency", b"reveal") => self.urgency = UrgencyPolicy::Reveal,
+			(b"windows", b"oldest") => self.windows = WindowChoice::Oldest,
+			(b"windows", b"newest") => self.windows = WindowChoice::Newest,
+			(b"windows", b"topmost") => self.windows = WindowChoice::Topmost,
+			(b"layout", b"none") => self.layout = Layout::None,
+			(b"layout", b"horizontal") => self.layout = Layout::Horizontal,
+			(b"layout", b"vertical") => self.layout = Layout::Vertical,
+			(b"layout", b"grid") => self.layout = Layout::Grid,
+			(b"vomit-on", b"last") => self.vomit_on = VomitCondition::Last,
+			(b"vomit-on", b"primary") => self.vomit_on = VomitCondition::Primary,
+			(b"vomit-on", b"exit") => self.vomit_on = VomitCondition::Exit,
+			(b"swallow", _) => {
+				let Some(inherit) = parse_inherit(value) else {
+					return false;
+				};
+				self.swallow = inherit;
+			}
+			(b"vomit", _) => {
+				let Some(inherit) = parse_inherit(value) else {
+					return false;
+				};
+				self.vomit = inherit;
+			}
+			_ => return false,
+		}
+		true
+	}
+}
+
+/// `+`-separated parts of a geometry, e.g. `center+desktop`
+fn parse_inherit(value: &[u8]) -> Option<Inherit> {
+	match value {
+		b"all" => return Some(Inherit::ALL),
+		b"none" => return Some(Inherit::NONE),
+		_ => {}
+	}
+	value
+		.split(|&c| c == b'+')
+		.try_fold(Inherit::NONE, |mut inherit, part| {
+			match part {
+				b"position" => inherit.placement = Placement::Copy,
+				b"center" => inherit.placement = Placement::Center,
+				b"size" => inherit.size = true,
+				b"desktop" => inherit.desktop = true,
+				b"state" => inherit.state = inherit.state.union(WindowState::DEFAULT),
+				// or just some of the state, e.g. `fullscreen`
+				_ => inherit.state = inherit.state.union(WindowState::from_name(part)?),
+			}
+			Some(inherit)
+		})
+}
+
+fn rule_name(entry: &[u8]) -> &[u8] {
+	entry.split(|&c| c == b',').next().unwrap_or_default()
+}
+
+/// `:`-separated list of `name,key=value,...`, where `name` is a process
+/// name, or `*` for every program without its own rule
+pub struct Rules {
+	default: Rule,
+	by_name: HashMap<Rc<[u8]>, Rule>,
+}
+
+impl Rules {
+	pub fn from_env(name: &str) -> Self {
+		Self::parse(name, &env_bytes(name))
+	}
+	/// `name` is only for reporting mistakes
+	fn parse(name: &str, text: &[u8]) -> Self {
+		let entries = env_iter(text).collect::<Vec<_>>();
+		let parse = |base: Rule, entry: &[u8]| {
+			let mut rule = base;
+			for setting in entry.split(|&c| c == b',').skip(1) {
+				let (key, value) = match setting.iter().position(|&c| c == b'=') {
+					Some(index) => (&setting[..index], &setting[index + 1..]),
+					None => (setting, &[][..]),
+				};
+				if !rule.set(key, value) {
+					output::bad_config(name, setting);
+				}
+			}
+			rule
+		};
+		// named rules start from `*`, wherever it is in the list
+		let default = entries
+			.iter()
+			.filter(|entry| rule_name(entry) == b"*")
+			.fold(Rule::default(), |rule, entry| parse(rule, entry));
+		let by_name = entries
+			.iter()
+			.filter(|entry| rule_name(entry) != b"*")
+			.map(|entry| (rule_name(entry).into(), parse(default, entry)))
+			.collect();
+		Self { default, by_name }
+	}
+	pub fn get(&self, name: &[u8]) -> Rule {
+		self.by_name.get(name).copied().unwrap_or(self.default)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{FocusPolicy, Rules, parse_inherit};
+	use crate::context::{HideStrategy, Inherit, Placement, WindowState};
+
+	#[test]
+	fn default_rule_comes_first() {
+		// `*` applies to named rules even if it's after them
+		let rules = Rules::parse("TEST", b"mpv,hide=iconify:*,hide=offscreen,focus=never");
+		assert!(matches!(rules.get(b"mpv").hide, HideStrategy::Iconify));
+		assert!(matches!(rules.get(b"mpv").focus, FocusPolicy::Never));
+		assert!(matches!(rules.get(b"feh").hide, HideStrategy::Offscreen));
+		// but a named rule's own settings win
+		let rules = Rules::parse("TEST", b"*,focus=never:mpv,focus=always");
+		assert!(matches!(rules.get(b"mpv").focus, FocusPolicy::Always));
+		assert!(matches!(rules.get(b"feh").focus, FocusPolicy::Never));
+	}
+
+	#[test]
+	fn bad_settings_are_skipped() {
+		let rules = Rules::parse("TEST", b"mpv,bogus=1,hide=iconify,hide=sideways,desktop=x");
+		assert!(matches!(rules.get(b"mpv").hide, HideStrategy::Iconify));
+		let rules = Rules::parse("TEST", b"mpv,desktop=3");
+		assert!(matches!(rules.get(b"mpv").hide, HideStrategy::Desktop(3)));
+		let rules = Rules::parse("TEST", b"mpv,desktop=4294967295");
+		assert!(matches!(rules.get(b"mpv").hide, HideStrategy::Unmap));
+		// nothing at all is the same as the defaults
+		let rules = Rules::parse("TEST", b"");
+		assert!(matches!(rules.get(b"mpv").hide, HideStrategy::Unmap));
+	}
+
+	#[test]
+	fn inherit_parts() {
+		let inherit = parse_inherit(b"center+desktop+fullscreen").unwrap();
+		assert!(matches!(inherit.placement, Placement::Center));
+		assert!(!inherit.size);
+		assert!(inherit.desktop);
+		assert_eq!(
+			inherit.state,
+			WindowState::from_name(b"fullscreen").unwrap()
+		);
+		let inherit = parse_inherit(b"position+size+state").unwrap();
+		assert!(matches!(inherit.placement, Placement::Copy));
+		assert!(inherit.size);
+		assert_eq!(inherit.state, WindowState::DEFAULT);
+		// the newer states only by name
+		let inherit = parse_inherit(b"state+skip_taskbar").unwrap();
+		assert_eq!(
+			inherit.state,
+			WindowState::DEFAULT.union(WindowState::from_name(b"skip_taskbar").unwrap())
+		);
+		assert!(matches!(
+			parse_inherit(b"all"),
+			Some(Inherit { size: true, .. })
+		));
+		assert!(matches!(
+			parse_inherit(b"none"),
+			Some(Inherit { size: false, .. })
+		));
+	}
+
+	#[test]
+	fn inherit_rejects_unknown() {
+		assert!(parse_inherit(b"position+bogus").is_none());
+		assert!(parse_inherit(b"position+").is_none());
+		assert!(parse_inherit(b"").is_none());
+	}
+}
@@ -1,26 +1,32 @@
 //! abstraction over xcb connection (and some relevant things)
 //! all raw xcb code goes here
+use std::cell::{Cell, RefCell};
 use std::collections::VecDeque;
 use std::convert::Infallible;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::str::from_utf8;
-use std::sync::Arc;
-use std::sync::mpsc::{Receiver, sync_channel};
-use std::{array, fmt, iter, thread};
+use std::io::{self, Read};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::time::{Duration, Instant};
+use std::{fmt, iter, mem, ptr};
 
+use foldhash::HashMap;
 use xcb::x::{self, Atom, Window};
-use xcb::{Connection, Xid};
+use xcb::{Connection, Cookie, Xid, randr};
 
 use crate::output;
+use crate::process::ProcessId;
 
-/// event sent from the poll thread
-enum ThreadEvent {
-	Quit,
-	Err(xcb::Error),
+/// event before being coalesced
+enum RawEvent {
+	Signal(u32),
+	Err(xcb::ProtocolError),
+	ConnectionLost(xcb::ConnError),
+	ProcessExit(ProcessId),
 	PropertyNotify(x::PropertyNotifyEvent),
+	SelectionClear(x::SelectionClearEvent),
 	ConfigureNotify(x::ConfigureNotifyEvent),
 	DestroyNotify(x::DestroyNotifyEvent),
+	ScreenChange,
 	Other,
 }
 
@@ -30,77 +36,247 @@ pub enum Event {
 	/// an unimportant event
 	Interrupted,
 	Quit,
+	/// another instance took over
+	Replaced,
+	/// print the current state (on `SIGUSR1`)
+	Dump,
+	/// the X server went away
+	ConnectionLost,
 	WindowList,
+	/// `_NET_CLIENT_LIST_STACKING` changed
+	Stacking,
+	/// window needs to be measured again
 	Update(Window),
+	/// `WM_HINTS` changed, e.g. the urgency hint
+	Hints(Window),
+	/// window moved or resized, and the event says where to
+	Configure(Window, Configure),
 	Close(Window),
+	ProcessExit(ProcessId),
+}
+
+/// contents of a `ConfigureNotify`, to avoid measuring the window again
+#[derive(Clone, Copy)]
+pub struct Configure {
+	x: i16,
+	y: i16,
+	w: u16,
+	h: u16,
+	synthetic: bool,
+}
+
+/// how many requests `Context::blame` can look back through
+const OPERATION_HISTORY: usize = 64;
+
+/// a request that was sent, for blaming errors on
+#[derive(Clone, Copy)]
+pub struct Operation {
+	sequence: u64,
+	pub name: &'static str,
+	pub window: Window,
+}
+
+/// every error type has a sequence number, but there's no trait for it
+fn error_sequence(err: &x::Error) -> u16 {
+	macro_rules! sequence {
+		($($variant:ident)*) => {
+			match err {
+				$(x::Error::$variant(err) => err.sequence(),)*
+			}
+		}
+	}
+	sequence!(
+		Request Value Window Pixmap Atom Cursor Font Match Drawable Access Alloc Colormap
+		GContext IdChoice Name Length Implementation
+	)
+}
+
+/// signals handled by the event loop instead of the default handler
+const SIGNALS: [i32; 4] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP, libc::SIGUSR1];
+
+/// signalfd for `SIGNALS`, unblocks them again when dropped
+struct Signals {
+	fd: File,
+	old_mask: libc::sigset_t,
+}
+
+impl Signals {
+	fn new() -> io::Result<Self> {
+		// SAFETY: all the pointers are to local variables,
+		// and there are no other threads to race with on the signal mask
+		unsafe {
+			let mut mask = mem::zeroed();
+			let mut old_mask = mem::zeroed();
+			libc::sigemptyset(&raw mut mask);
+			for signal in SIGNALS {
+				libc::sigaddset(&raw mut mask, signal);
+			}
+			if libc::sigprocmask(libc::SIG_BLOCK, &raw const mask, &raw mut old_mask) < 0 {
+				return Err(io::Error::last_os_error());
+			}
+			let fd = libc::signalfd(-1, &raw const mask, libc::SFD_CLOEXEC | libc::SFD_NONBLOCK);
+			if fd < 0 {
+				let err = io::Error::last_os_error();
+				libc::sigprocmask(libc::SIG_SETMASK, &raw const old_mask, ptr::null_mut());
+				return Err(err);
+			}
+			Ok(Self {
+				fd: File::from(OwnedFd::from_raw_fd(fd)),
+				old_mask,
+			})
+		}
+	}
+	/// next pending signal, if any
+	fn read(&self) -> Option<u32> {
+		let mut info = [0; size_of::<libc::signalfd_siginfo>()];
+		(&self.fd).read_exact(&mut info).ok()?;
+		// `ssi_signo` is the first field
+		Some(u32::from_ne_bytes(info[..4].try_into().ok()?))
+	}
+}
+
+impl Drop for Signals {
+	fn drop(&mut self) {
+		// SAFETY: same as in `new`
+		unsafe {
+			libc::sigprocmask(libc::SIG_SETMASK, &raw const self.old_mask, ptr::null_mut());
+		}
+	}
 }
 
-/// `_NET_WM_STATE` in a bitfield
-#[derive(Debug, Clone, Copy)]
+/// state of the poll loop in `Context::next_event`
+struct EventLoop {
+	signals: Signals,
+	/// pidfds from `Context::watch_process`
+	processes: RefCell<Vec<(ProcessId, OwnedFd)>>,
+	/// coalesced events that haven't been returned yet
+	queue: RefCell<VecDeque<Event>>,
+	/// minimum time between batches
+	interval: Duration,
+	last_batch: Cell<Instant>,
+	/// server time of the latest event, for requests that need a timestamp
+	last_time: Cell<x::Timestamp>,
+}
+
+/// `_NET_WM_STATE` in a bitfield, in the same order as `WindowState::atoms`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(transparent)]
-struct WindowState(u8);
+pub struct WindowState(u16);
 impl WindowState {
+	/// lowercase atom names without the `_NET_WM_STATE_`, for configuration
+	const NAMES: [&str; 13] = [
+		"maximized_vert",
+		"maximized_horz",
+		"sticky",
+		"shaded",
+		"hidden",
+		"fullscreen",
+		"above",
+		"below",
+		"modal",
+		"skip_taskbar",
+		"skip_pager",
+		"demands_attention",
+		"focused",
+	];
+	/// the ones up to `below`, what `state` and `all` mean, the rest only get
+	/// copied when they're asked for by name
+	pub const DEFAULT: Self = Self(0xFF);
+	const HIDDEN: Self = Self(0x10);
+	pub const DEMANDS_ATTENTION: Self = Self(0x800);
+	const FOCUSED: Self = Self(0x1000);
+	/// maximized or fullscreen, which would undo tiling
+	const FILLING: Self = Self(0x23);
+	fn atoms(cx: &Context) -> [Atom; Self::NAMES.len()] {
+		[
+			cx.atom_state_max_vert,
+			cx.atom_state_max_horz,
+			cx.atom_state_sticky,
+			cx.atom_state_shaded,
+			cx.atom_state_hidden,
+			cx.atom_state_fullscreen,
+			cx.atom_state_above,
+			cx.atom_state_below,
+			cx.atom_state_modal,
+			cx.atom_state_skip_taskbar,
+			cx.atom_state_skip_pager,
+			cx.atom_state_demands_attention,
+			cx.atom_state_focused,
+		]
+	}
 	fn new(cx: &Context, list: &[Atom]) -> Self {
-		// TODO: there's a better way to do this match
-		Self(list.iter().fold(0, |out, &i| {
-			out | match i {
-				i if i == cx.atom_state_max_vert => 0x01,
-				i if i == cx.atom_state_max_horz => 0x02,
-				i if i == cx.atom_state_sticky => 0x04,
-				i if i == cx.atom_state_shaded => 0x08,
-				i if i == cx.atom_state_hidden => 0x10,
-				i if i == cx.atom_state_fullscreen => 0x20,
-				i if i == cx.atom_state_above => 0x40,
-				i if i == cx.atom_state_below => 0x80,
-				_ => 0,
-			}
+		let atoms = Self::atoms(cx);
+		Self(list.iter().fold(0, |out, atom| {
+			out | atoms
+				.iter()
+				.position(|other| other == atom)
+				.map_or(0, |index| 1 << index)
 		}))
 	}
-	fn take_one(&mut self) -> usize {
+	/// single state by its name in `NAMES`
+	pub fn from_name(name: &[u8]) -> Option<Self> {
+		let index = Self::NAMES
+			.iter()
+			.position(|other| other.as_bytes() == name)?;
+		Some(Self(1 << index))
+	}
+	pub const fn union(self, other: Self) -> Self {
+		Self(self.0 | other.0)
+	}
+	#[must_use]
+	pub const fn without(self, other: Self) -> Self {
+		Self(self.0 & !other.0)
+	}
+	/// `self` with the states in `mask` taken from `other`
+	#[must_use]
+	const fn merge(self, other: Self, mask: Self) -> Self {
+		Self(self.0 & !mask.0 | other.0 & mask.0)
+	}
+	fn take_one(&mut self) -> Option<usize> {
 		let index = self.0.trailing_zeros();
-		self.0 &= !1_u8.wrapping_shl(index);
-		index as usize
-	}
-	/// list of state events needed to make a window enter this state.
-	/// does not update the minimized state
-	fn events(self, cx: &Context) -> [[u32; 5]; 4] {
-		// TODO: write a proof for this
-		// ∀n. ceil(n/2) + ceil((7-n)/2) = 4
-		let atoms = [
-			cx.atom_state_max_vert.resource_id(),
-			cx.atom_state_max_horz.resource_id(),
-			cx.atom_state_sticky.resource_id(),
-			cx.atom_state_shaded.resource_id(),
-			cx.atom_state_hidden.resource_id(),
-			cx.atom_state_fullscreen.resource_id(),
-			cx.atom_state_above.resource_id(),
-			cx.atom_state_below.resource_id(),
-			x::ATOM_NONE.resource_id(),
-		];
-		let mut next = Self(self.0 & !0x10);
-		let mut state = 1;
-		array::from_fn(|_| {
-			if next.0 == 0 {
-				next = Self(!self.0 & !0x10);
-				state = 0;
-			};
-			[state, atoms[next.take_one()], atoms[next.take_one()], 2, 0]
-		})
+		self.0 &= !1_u16.checked_shl(index)?;
+		Some(index as usize)
+	}
+	/// list of state events needed to make a window enter this state, only
+	/// touching the states in `managed`.
+	/// does not update the minimized state, and focus is up to the window
+	/// manager
+	fn events(self, cx: &Context, managed: Self) -> Vec<[u32; 5]> {
+		let atoms = Self::atoms(cx);
+		let settable = managed.0 & !Self::HIDDEN.0 & !Self::FOCUSED.0;
+		// each message can add or remove two states at once
+		[(1, Self(self.0 & settable)), (0, Self(!self.0 & settable))]
+			.into_iter()
+			.flat_map(|(action, mut states)| {
+				iter::from_fn(move || {
+					let first = atoms[states.take_one()?].resource_id();
+					let second = states
+						.take_one()
+						.map_or(x::ATOM_NONE.resource_id(), |index| {
+							atoms[index].resource_id()
+						});
+					Some([action, first, second, 2, 0])
+				})
+			})
+			.collect()
 	}
 	/// iterator of short property names
 	#[cfg_attr(rust_analyzer, expect(unused_mut, reason = "rust-analyzer#18209"))]
 	fn names(mut self) -> impl Iterator<Item = &'static str> {
-		const ATOM_NAMES: &str = "+V+H+S-S-M+M+O-O";
+		const ATOM_NAMES: &str = "+V+H+S-S-M+M+O-O!M-T-P!A+F";
 		iter::from_fn(move || {
-			let atom = self.take_one() * 2;
+			let atom = self.take_one()? * 2;
 			ATOM_NAMES.get(atom..atom + 2)
 		})
 	}
 	fn is_hidden(self) -> bool {
-		self.0 & 0x10 != 0
+		self.0 & Self::HIDDEN.0 != 0
 	}
 }
 
+/// `_NET_WM_DESKTOP` for windows on every desktop
+const ALL_DESKTOPS: u32 = 0xFFFF_FFFF;
+
 /// where a window is
 #[derive(Clone, Copy)]
 pub struct Geometry {
@@ -108,14 +284,371 @@ pub struct Geometry {
 	y: i16,
 	w: u16,
 	h: u16,
+	/// can be `ALL_DESKTOPS`
 	d: u32,
 	s: WindowState,
+	/// which parts of `s` get applied, the rest are left as the window has
+	/// them, not stored
+	managed: WindowState,
+	/// position of the window inside its frame, not stored or applied
+	fx: i16,
+	fy: i16,
+	/// how much wider and taller the frame is than the window, not stored
+	fw: u16,
+	fh: u16,
+	/// whether it's inside a window manager's frame, so real `ConfigureNotify`s
+	/// are relative to that, not stored either
+	framed: bool,
+	/// the monitor it was on, as that monitor was at the time
+	m: Option<Monitor>,
+}
+
+/// where a window goes relative to the one it's replacing
+#[derive(Clone, Copy)]
+pub enum Placement {
+	/// stay where it is
+	Keep,
+	/// same top-left corner
+	Copy,
+	/// same centre, for windows that keep their own size
+	Center,
+}
+
+/// how the children of one parent share its space
+#[derive(Clone, Copy)]
+pub enum Layout {
+	/// only the first child takes its place, later ones stay where they are
+	None,
+	/// side by side
+	Horizontal,
+	/// on top of each other
+	Vertical,
+	/// rows and columns, as square as possible
+	Grid,
+}
+
+/// which parts of a `Geometry` one window takes from another
+#[derive(Clone, Copy)]
+pub struct Inherit {
+	pub placement: Placement,
+	pub size: bool,
+	pub desktop: bool,
+	/// which parts of `_NET_WM_STATE`, e.g. maximized or fullscreen
+	pub state: WindowState,
+}
+
+impl Inherit {
+	pub const ALL: Self = Self {
+		placement: Placement::Copy,
+		size: true,
+		desktop: true,
+		state: WindowState::DEFAULT,
+	};
+	pub const NONE: Self = Self {
+		placement: Placement::Keep,
+		size: false,
+		desktop: false,
+		state: WindowState(0),
+	};
+	/// a tile from `Geometry::tile`, which isn't maximized or fullscreen
+	pub const TILE: Self = Self {
+		placement: Placement::Copy,
+		size: true,
+		desktop: true,
+		state: WindowState::FILLING,
+	};
+}
+
+impl Geometry {
+	/// take the parts of `other` that `inherit` asks for
+	#[must_use]
+	pub fn inherit(mut self, other: Self, inherit: Inherit) -> Self {
+		if inherit.size {
+			(self.w, self.h) = (other.w, other.h);
+		}
+		if !matches!(inherit.placement, Placement::Keep) {
+			self.m = other.m;
+		}
+		(self.x, self.y) = match inherit.placement {
+			Placement::Keep => (self.x, self.y),
+			Placement::Copy => (other.x, other.y),
+			Placement::Center => (
+				center(other.x, other.w, self.w),
+				center(other.y, other.h, self.h),
+			),
+		};
+		if inherit.desktop {
+			self.d = other.d;
+		}
+		self.s = self.s.merge(other.s, inherit.state);
+		self.managed = self.managed.union(inherit.state);
+		self
+	}
+	/// the `index`th of `count` pieces of this rectangle including its frame,
+	/// `None` if the layout doesn't tile. use with `fit_frame`, since the size
+	/// leaves room for this window's frame, not the one that goes there
+	pub fn tile(self, layout: Layout, index: usize, count: usize) -> Option<Self> {
+		let (columns, rows) = match layout {
+			Layout::None => return None,
+			Layout::Horizontal => (count, 1),
+			Layout::Vertical => (1, count),
+			Layout::Grid => {
+				let columns = count.isqrt() + usize::from(count.isqrt().pow(2) < count);
+				(columns, count.div_ceil(columns))
+			}
+		};
+		let (column, row) = (index % columns, index / columns);
+		// the last row might not be full, so its windows get wider
+		let row_columns = columns.min(count - row * columns);
+		let (x, w) = split(self.x, self.w.saturating_add(self.fw), column, row_columns);
+		let (y, h) = split(self.y, self.h.saturating_add(self.fh), row, rows);
+		Some(Self {
+			x,
+			y,
+			w: w.saturating_sub(self.fw),
+			h: h.saturating_sub(self.fh),
+			s: self.s.merge(WindowState(0), WindowState::FILLING),
+			..self
+		})
+	}
+	/// same size as `other` including the frames, which can be different
+	#[must_use]
+	pub fn fit_frame(mut self, other: Self) -> Self {
+		self.w = other.w.saturating_add(other.fw).saturating_sub(self.fw);
+		self.h = other.h.saturating_add(other.fh).saturating_sub(self.fh);
+		self
+	}
+	/// update from a `ConfigureNotify` instead of measuring again,
+	/// false if it needs measuring anyway
+	#[must_use]
+	pub fn configure(&mut self, configure: Configure) -> bool {
+		let Configure {
+			x,
+			y,
+			w,
+			h,
+			synthetic,
+		} = configure;
+		if synthetic {
+			(self.x, self.y) = (x - self.fx, y - self.fy);
+		} else if self.framed {
+			// real events are relative to the frame
+			(self.fx, self.fy) = (x, y);
+		} else {
+			// or the root, but then the frame extents aren't in them, or it
+			// might have been reparented since it was measured
+			return false;
+		}
+		(self.w, self.h) = (w, h);
+		true
+	}
+	/// for storing in a `CARDINAL[11]` property, the last 5 are the monitor
+	fn to_cardinals(self) -> [u32; 11] {
+		let Self {
+			x,
+			y,
+			w,
+			h,
+			d,
+			s,
+			m,
+			..
+		} = self;
+		let [mn, mx, my, mw, mh] = m.map_or([0; 5], Monitor::to_cardinals);
+		[
+			x.cast_unsigned().into(),
+			y.cast_unsigned().into(),
+			w.into(),
+			h.into(),
+			d,
+			s.0.into(),
+			mn,
+			mx,
+			my,
+			mw,
+			mh,
+		]
+	}
+	#[expect(clippy::many_single_char_names, reason = "same names as the fields")]
+	fn from_cardinals(list: &[u32]) -> Option<Self> {
+		// saved by a version that didn't know about monitors
+		let (&[x, y, w, h, d, s], monitor) = (list.get(..6)?, list.get(6..)) else {
+			return None;
+		};
+		Some(Self {
+			x: u16::try_from(x).ok()?.cast_signed(),
+			y: u16::try_from(y).ok()?.cast_signed(),
+			w: w.try_into().ok()?,
+			h: h.try_into().ok()?,
+			d,
+			s: WindowState(s.try_into().ok()?),
+			managed: WindowState::DEFAULT,
+			fx: 0,
+			fy: 0,
+			fw: 0,
+			fh: 0,
+			framed: false,
+			m: monitor.and_then(Monitor::from_cardinals),
+		})
+	}
+	fn center_point(self) -> (i32, i32) {
+		(
+			i32::from(self.x) + i32::from(self.w) / 2,
+			i32::from(self.y) + i32::from(self.h) / 2,
+		)
+	}
+}
+
+/// an `XRandR` monitor, or the whole screen without it
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Monitor {
+	/// name atom, 0 for the whole screen
+	name: u32,
+	x: i16,
+	y: i16,
+	w: u16,
+	h: u16,
+}
+
+impl Monitor {
+	fn to_cardinals(self) -> [u32; 5] {
+		[
+			self.name,
+			self.x.cast_unsigned().into(),
+			self.y.cast_unsigned().into(),
+			self.w.into(),
+			self.h.into(),
+		]
+	}
+	fn from_cardinals(list: &[u32]) -> Option<Self> {
+		let &[name, x, y, w, h] = list else {
+			return None;
+		};
+		(name != 0).then_some(Self {
+			name,
+			x: u16::try_from(x).ok()?.cast_signed(),
+			y: u16::try_from(y).ok()?.cast_signed(),
+			w: w.try_into().ok()?,
+			h: h.try_into().ok()?,
+		})
+	}
+	fn contains(self, (x, y): (i32, i32)) -> bool {
+		(i32::from(self.x)..i32::from(self.x) + i32::from(self.w)).contains(&x)
+			&& (i32::from(self.y)..i32::from(self.y) + i32::from(self.h)).contains(&y)
+	}
+}
+
+/// where `start` ends up when the span it's in moves from `old` to `new`,
+/// keeping a `size` long window inside of `new` if it was moved
+fn remap_span(start: i16, size: u16, old: (i16, u16), new: (i16, u16)) -> i16 {
+	let start = i32::from(start) - i32::from(old.0) + i32::from(new.0);
+	let end = i32::from(new.0) + i32::from(new.1) - i32::from(size);
+	let start = start.min(end).max(new.0.into());
+	i16::try_from(start).unwrap_or(new.0)
+}
+
+/// the parts of `WM_NORMAL_HINTS` that limit a window's size
+#[derive(Clone, Copy, Default)]
+struct SizeHints {
+	min: Option<(u64, u64)>,
+	max: Option<(u64, u64)>,
+	increment: Option<(u64, u64)>,
+	/// minimum and maximum width:height
+	aspect: Option<((u64, u64), (u64, u64))>,
+	base: Option<(u64, u64)>,
+}
+
+impl SizeHints {
+	fn from_cardinals(list: &[u32]) -> Self {
+		// flags from ICCCM 4.1.2.3
+		const P_MIN_SIZE: u32 = 16;
+		const P_MAX_SIZE: u32 = 32;
+		const P_RESIZE_INC: u32 = 64;
+		const P_ASPECT: u32 = 128;
+		const P_BASE_SIZE: u32 = 256;
+		let [flags, _, _, _, _, ref fields @ ..] = *list else {
+			return Self::default();
+		};
+		let pair = |flag: u32, index: usize| {
+			let &[a, b] = fields.get(index..index + 2)? else {
+				return None;
+			};
+			(flags & flag != 0).then_some((a.into(), b.into()))
+		};
+		Self {
+			min: pair(P_MIN_SIZE, 0),
+			max: pair(P_MAX_SIZE, 2),
+			increment: pair(P_RESIZE_INC, 4),
+			aspect: pair(P_ASPECT, 6).zip(pair(P_ASPECT, 8)),
+			base: pair(P_BASE_SIZE, 10),
+		}
+	}
+	/// biggest size that fits in `w`×`h` and that the window would accept,
+	/// unless the window's minimum size is bigger
+	fn fit(self, w: u16, h: u16) -> (u16, u16) {
+		let (mut w, mut h) = (u64::from(w), u64::from(h));
+		if let Some((max_w, max_h)) = self.max {
+			(w, h) = (w.min(max_w), h.min(max_h));
+		}
+		// aspect ratio doesn't include the base size, increments use the
+		// minimum size if there isn't one
+		let (base_w, base_h) = self.base.unwrap_or_default();
+		if let Some(((min_x, min_y), (max_x, max_y))) = self.aspect {
+			let (aspect_w, aspect_h) = (w.saturating_sub(base_w), h.saturating_sub(base_h));
+			if max_y > 0 && aspect_w * max_y > aspect_h * max_x {
+				// too wide
+				w = base_w + aspect_h * max_x / max_y;
+			} else if min_x > 0 && aspect_w * min_y < aspect_h * min_x {
+				// too tall
+				h = base_h + aspect_w * min_y / min_x;
+			}
+		}
+		if let Some((inc_w, inc_h)) = self.increment {
+			let (base_w, base_h) = self.base.or(self.min).unwrap_or_default();
+			if inc_w > 0 && w > base_w {
+				w = base_w + (w - base_w) / inc_w * inc_w;
+			}
+			if inc_h > 0 && h > base_h {
+				h = base_h + (h - base_h) / inc_h * inc_h;
+			}
+		}
+		if let Some((min_w, min_h)) = self.min {
+			(w, h) = (w.max(min_w), h.max(min_h));
+		}
+		(
+			w.try_into().unwrap_or(u16::MAX),
+			h.try_into().unwrap_or(u16::MAX),
+		)
+	}
+}
+
+/// the `index`th of `count` equal parts of a span, without gaps between them
+fn split(start: i16, size: u16, index: usize, count: usize) -> (i16, u16) {
+	let edge = |index: usize| {
+		let offset = usize::from(size) * index / count.max(1);
+		u16::try_from(offset).unwrap_or(size)
+	};
+	let (from, to) = (edge(index), edge(index + 1));
+	(start.saturating_add_unsigned(from), to - from)
+}
+
+/// start of a `size` long span centred on the `outer_size` long one at `outer`
+fn center(outer: i16, outer_size: u16, size: u16) -> i16 {
+	let offset = (i32::from(outer_size) - i32::from(size)) / 2;
+	i16::try_from(i32::from(outer) + offset).unwrap_or(outer)
 }
 
 impl fmt::Display for Geometry {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		let Self { x, y, w, h, d, s } = self;
-		write!(f, "{w}x{h}+{x},{y}@{d}")?;
+		let Self {
+			x, y, w, h, d, s, ..
+		} = self;
+		write!(f, "{w}x{h}+{x},{y}@")?;
+		if *d == ALL_DESKTOPS {
+			write!(f, "all")?;
+		} else {
+			write!(f, "{d}")?;
+		}
 		for entry in s.names() {
 			write!(f, "{entry}")?;
 		}
@@ -123,19 +656,64 @@ impl fmt::Display for Geometry {
 	}
 }
 
+/// how a swallowed parent gets out of the way
+#[derive(Clone, Copy)]
+pub enum HideStrategy {
+	/// some window managers forget where an unmapped window was
+	Unmap,
+	/// minimize, stays on the taskbar
+	Iconify,
+	/// move to a desktop nobody looks at
+	Desktop(u32),
+	/// move past the top-left corner of the screen
+	Offscreen,
+}
+
+impl HideStrategy {
+	/// for storing in a `CARDINAL[2]` property
+	fn to_cardinals(self) -> [u32; 2] {
+		match self {
+			Self::Unmap => [0, 0],
+			Self::Iconify => [1, 0],
+			Self::Desktop(desktop) => [2, desktop],
+			Self::Offscreen => [3, 0],
+		}
+	}
+	fn from_cardinals(list: &[u32]) -> Option<Self> {
+		match *list {
+			[0, _] => Some(Self::Unmap),
+			[1, _] => Some(Self::Iconify),
+			[2, desktop] => Some(Self::Desktop(desktop)),
+			[3, _] => Some(Self::Offscreen),
+			_ => None,
+		}
+	}
+}
+
 // so i don't need to write out atom_ a bunch of times
 macro_rules! intern {
-	($Context:ident $connection:ident $rx:ident $root:ident $new:ident, $($var:ident = $name:literal,)*) => {
+	($Context:ident $connection:ident $events:ident $operations:ident $root:ident $selection:ident $monitors:ident $desktops:ident $supported:ident $new:ident, $($var:ident = $name:literal,)*) => {
 		pub struct $Context {
-			$connection: Arc<Connection>,
-			$rx: Receiver<ThreadEvent>,
+			$connection: Connection,
+			$events: EventLoop,
+			/// recently sent requests, see `Context::send`
+			$operations: RefCell<VecDeque<Operation>>,
 			$root: Window,
+			$selection: Atom,
+			/// kept up to date with `RRScreenChangeNotify`
+			$monitors: RefCell<Vec<Monitor>>,
+			/// `_NET_NUMBER_OF_DESKTOPS`, 0 if the window manager doesn't say
+			$desktops: Cell<u32>,
+			/// `_NET_SUPPORTED`, kept up to date since the window manager can be
+			/// replaced
+			$supported: RefCell<Vec<Atom>>,
 			$($var: Atom,)*
 		}
 		impl $Context {
-			pub fn $new() -> xcb::Result<Self> {
-				let ($connection, screen) = Connection::connect(None)?;
-				let $connection = Arc::new($connection);
+			/// `None` if another instance is running and `replace` isn't set
+			pub fn $new(replace: bool, update_interval: Duration) -> xcb::Result<Option<Self>> {
+				let ($connection, screen) =
+					Connection::connect_with_extensions(None, &[], &[xcb::Extension::RandR])?;
 				let $root = $connection
 					.get_setup()
 					.roots()
@@ -151,24 +729,70 @@ macro_rules! intern {
 					name: $name.as_bytes(),
 				});)*
 				$(let $var = $connection.wait_for_reply($var)?.atom();)*
-				let $rx = event_thread(Arc::clone(&$connection));
+				let Some($selection) = acquire_selection(&$connection, $root, screen, replace)? else {
+					return Ok(None);
+				};
+				if has_randr(&$connection) {
+					// monitors need 1.5
+					$connection.wait_for_reply($connection.send_request(&randr::QueryVersion {
+						major_version: 1,
+						minor_version: 5,
+					}))?;
+					$connection.send_request(&randr::SelectInput {
+						window: $root,
+						enable: randr::NotifyMask::SCREEN_CHANGE,
+					});
+				}
+				let $monitors = RefCell::new(get_monitors(&$connection, $root));
+				let $events = EventLoop {
+					signals: Signals::new().expect("Failed to set up signalfd"),
+					processes: RefCell::default(),
+					queue: RefCell::default(),
+					interval: update_interval,
+					last_batch: Cell::new(Instant::now()),
+					last_time: Cell::new(x::CURRENT_TIME),
+				};
 				output::setup_context(screen, $root, &[$($var,)*]);
-				Ok(Self {
+				let this = Self {
 					$connection,
-					$rx,
+					$events,
+					$operations: RefCell::new(VecDeque::with_capacity(OPERATION_HISTORY)),
 					$root,
+					$selection,
+					$monitors,
+					$desktops: Cell::new(0),
+					$supported: RefCell::default(),
 					$($var,)*
-				})
+				};
+				this.update_desktops();
+				this.update_supported();
+				Ok(Some(this))
 			}
 		}
 	}
 }
 intern!(
-	Context connection rx root new,
+	Context connection events operations root selection monitors desktops supported new,
 	atom_active_window = "_NET_ACTIVE_WINDOW",
+	atom_close_window = "_NET_CLOSE_WINDOW",
 	atom_client_list = "_NET_CLIENT_LIST",
+	atom_client_list_stacking = "_NET_CLIENT_LIST_STACKING",
+	atom_restack_window = "_NET_RESTACK_WINDOW",
+	atom_user_time = "_NET_WM_USER_TIME",
 	atom_pid = "_NET_WM_PID",
+	atom_startup_id = "_NET_STARTUP_ID",
+	atom_utf8_string = "UTF8_STRING",
+	// xswallow's own state, so it survives restarts
+	atom_saved_parents = "_XSWALLOW_PARENTS",
+	atom_saved_parent = "_XSWALLOW_PARENT",
+	atom_saved_geometry = "_XSWALLOW_GEOMETRY",
+	atom_saved_hide = "_XSWALLOW_HIDE",
 	atom_desktop = "_NET_WM_DESKTOP",
+	atom_number_of_desktops = "_NET_NUMBER_OF_DESKTOPS",
+	atom_supported = "_NET_SUPPORTED",
+	atom_frame_extents = "_NET_FRAME_EXTENTS",
+	atom_request_frame_extents = "_NET_REQUEST_FRAME_EXTENTS",
+	atom_moveresize_window = "_NET_MOVERESIZE_WINDOW",
 	// from ICCCM, not a typo
 	atom_change_state = "WM_CHANGE_STATE",
 	atom_state = "_NET_WM_STATE",
@@ -180,62 +804,413 @@ intern!(
 	atom_state_fullscreen = "_NET_WM_STATE_FULLSCREEN",
 	atom_state_above = "_NET_WM_STATE_ABOVE",
 	atom_state_below = "_NET_WM_STATE_BELOW",
+	atom_state_modal = "_NET_WM_STATE_MODAL",
+	atom_state_skip_taskbar = "_NET_WM_STATE_SKIP_TASKBAR",
+	atom_state_skip_pager = "_NET_WM_STATE_SKIP_PAGER",
+	atom_state_demands_attention = "_NET_WM_STATE_DEMANDS_ATTENTION",
+	atom_state_focused = "_NET_WM_STATE_FOCUSED",
 );
 
-// TODO: when adding an ipc interface do it here
-fn event_thread(connection: Arc<Connection>) -> Receiver<ThreadEvent> {
-	let (tx, rx) = sync_channel(0);
-	let thread = move || {
-		let inner_tx = tx.clone();
-		_ = ctrlc::set_handler(move || {
-			_ = inner_tx.send(ThreadEvent::Quit);
+fn has_randr(connection: &Connection) -> bool {
+	connection
+		.active_extensions()
+		.any(|extension| extension == xcb::Extension::RandR)
+}
+
+fn get_randr_monitors(connection: &Connection, root: Window) -> Option<Vec<Monitor>> {
+	if !has_randr(connection) {
+		return None;
+	}
+	let reply = connection
+		.wait_for_reply(connection.send_request(&randr::GetMonitors {
+			window: root,
+			get_active: true,
+		}))
+		.ok()?;
+	let mut monitors = reply.monitors().collect::<Vec<_>>();
+	// the primary monitor goes first, for windows whose monitor is gone
+	monitors.sort_by_key(|info| !info.primary());
+	let monitors = monitors
+		.into_iter()
+		.map(|info| Monitor {
+			name: info.name().resource_id(),
+			x: info.x(),
+			y: info.y(),
+			w: info.width(),
+			h: info.height(),
+		})
+		.collect::<Vec<_>>();
+	(!monitors.is_empty()).then_some(monitors)
+}
+
+/// every active monitor, or just the screen if `XRandR` is missing
+fn get_monitors(connection: &Connection, root: Window) -> Vec<Monitor> {
+	get_randr_monitors(connection, root)
+		.or_else(|| {
+			let screen = connection
+				.wait_for_reply(connection.send_request(&x::GetGeometry {
+					drawable: x::Drawable::Window(root),
+				}))
+				.ok()?;
+			Some(vec![Monitor {
+				name: 0,
+				x: 0,
+				y: 0,
+				w: screen.width(),
+				h: screen.height(),
+			}])
+		})
+		.unwrap_or_default()
+}
+
+/// wait until any of `fds` is readable, retrying if interrupted.
+/// returns false if `timeout` (in milliseconds, -1 for none) ran out
+fn poll(fds: &mut [libc::pollfd], timeout: i32) -> io::Result<bool> {
+	loop {
+		// SAFETY: the pointer and length come from the same slice
+		let res = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) };
+		if res >= 0 {
+			return Ok(res > 0);
+		}
+		let err = io::Error::last_os_error();
+		if err.kind() != io::ErrorKind::Interrupted {
+			return Err(err);
+		}
+	}
+}
+
+fn pollfd(fd: &impl AsRawFd) -> libc::pollfd {
+	libc::pollfd {
+		fd: fd.as_raw_fd(),
+		events: libc::POLLIN,
+		revents: 0,
+	}
+}
+
+/// wait for an event that `check` accepts, for use before the event loop
+/// starts. returns `None` if it didn't arrive within `timeout` milliseconds
+fn wait_for_event_matching<T>(
+	connection: &Connection,
+	timeout: i32,
+	mut check: impl FnMut(xcb::Event) -> Option<T>,
+) -> xcb::Result<Option<T>> {
+	loop {
+		while let Some(event) = connection.poll_for_event()? {
+			if let Some(out) = check(event) {
+				return Ok(Some(out));
+			}
+		}
+		connection.flush()?;
+		match poll(&mut [pollfd(connection)], timeout) {
+			Ok(true) => {}
+			Ok(false) => return Ok(None),
+			Err(err) => output::error(err),
+		}
+	}
+}
+
+/// take the ICCCM manager selection `_XSWALLOW_S{screen}`,
+/// so two instances don't fight over the same windows
+fn acquire_selection(
+	connection: &Connection,
+	root: Window,
+	screen: i32,
+	replace: bool,
+) -> xcb::Result<Option<Atom>> {
+	let name = format!("_XSWALLOW_S{screen}");
+	let selection = connection.send_request(&x::InternAtom {
+		only_if_exists: false,
+		name: name.as_bytes(),
+	});
+	let manager = connection.send_request(&x::InternAtom {
+		only_if_exists: false,
+		name: b"MANAGER",
+	});
+	let selection = connection.wait_for_reply(selection)?.atom();
+	let manager = connection.wait_for_reply(manager)?.atom();
+	let old_owner = connection
+		.wait_for_reply(connection.send_request(&x::GetSelectionOwner { selection }))?
+		.owner();
+	if !old_owner.is_none() {
+		if !replace {
+			output::already_running(old_owner);
+			return Ok(None);
+		}
+		// to find out when it's done quitting
+		connection.send_request(&x::ChangeWindowAttributes {
+			window: old_owner,
+			value_list: &[x::Cw::EventMask(x::EventMask::STRUCTURE_NOTIFY)],
 		});
-		loop {
-			let event = match connection.wait_for_event() {
-				Err(err) => ThreadEvent::Err(err),
-				Ok(xcb::Event::X(x::Event::PropertyNotify(evt))) => {
-					ThreadEvent::PropertyNotify(evt)
-				}
-				Ok(xcb::Event::X(x::Event::ConfigureNotify(evt))) => {
-					ThreadEvent::ConfigureNotify(evt)
-				}
-				Ok(xcb::Event::X(x::Event::DestroyNotify(evt))) => ThreadEvent::DestroyNotify(evt),
-				Ok(_) => ThreadEvent::Other,
-			};
-			let Ok(()) = tx.send(event) else { break };
+	}
+	let window = connection.generate_id();
+	connection.send_request(&x::CreateWindow {
+		depth: 0,
+		wid: window,
+		parent: root,
+		x: -1,
+		y: -1,
+		width: 1,
+		height: 1,
+		border_width: 0,
+		class: x::WindowClass::InputOnly,
+		visual: x::COPY_FROM_PARENT,
+		value_list: &[
+			x::Cw::OverrideRedirect(true),
+			x::Cw::EventMask(x::EventMask::PROPERTY_CHANGE),
+		],
+	});
+	// ICCCM doesn't allow CurrentTime here, so get a real timestamp
+	connection.send_request(&x::ChangeProperty {
+		mode: x::PropMode::Append,
+		window,
+		property: selection,
+		r#type: x::ATOM_STRING,
+		data: &[] as &[u8],
+	});
+	let time = wait_for_event_matching(connection, -1, |event| match event {
+		xcb::Event::X(x::Event::PropertyNotify(event)) if event.window() == window => {
+			Some(event.time())
 		}
-	};
-	thread::Builder::new()
-		.name("Event Thread".into())
-		.spawn(thread)
-		.expect("Failed to start event thread");
-	rx
+		_ => None,
+	})?
+	.unwrap_or(x::CURRENT_TIME);
+	connection.send_request(&x::SetSelectionOwner {
+		owner: window,
+		selection,
+		time,
+	});
+	let owner = connection
+		.wait_for_reply(connection.send_request(&x::GetSelectionOwner { selection }))?
+		.owner();
+	if owner != window {
+		output::already_running(owner);
+		return Ok(None);
+	}
+	if !old_owner.is_none() {
+		output::replacing(old_owner);
+		let destroyed = wait_for_event_matching(connection, 5000, |event| match event {
+			xcb::Event::X(x::Event::DestroyNotify(event)) if event.window() == old_owner => {
+				Some(())
+			}
+			_ => None,
+		})?;
+		if destroyed.is_none() {
+			output::replace_timeout(old_owner);
+		}
+	}
+	connection.send_request(&x::SendEvent {
+		propagate: false,
+		destination: x::SendEventDest::Window(root),
+		event_mask: x::EventMask::STRUCTURE_NOTIFY,
+		event: &x::ClientMessageEvent::new(
+			root,
+			manager,
+			x::ClientMessageData::Data32([
+				time,
+				selection.resource_id(),
+				window.resource_id(),
+				0,
+				0,
+			]),
+		),
+	});
+	Ok(Some(selection))
+}
+
+fn raw_event(event: xcb::Event) -> RawEvent {
+	match event {
+		xcb::Event::X(x::Event::PropertyNotify(evt)) => RawEvent::PropertyNotify(evt),
+		xcb::Event::X(x::Event::SelectionClear(evt)) => RawEvent::SelectionClear(evt),
+		xcb::Event::X(x::Event::ConfigureNotify(evt)) => RawEvent::ConfigureNotify(evt),
+		xcb::Event::X(x::Event::DestroyNotify(evt)) => RawEvent::DestroyNotify(evt),
+		xcb::Event::RandR(randr::Event::ScreenChangeNotify(_)) => RawEvent::ScreenChange,
+		_ => RawEvent::Other,
+	}
 }
 
 impl Context {
 	// TODO: this feels very swallow-specific
 	pub fn next_event(&self) -> Event {
-		match self.rx.recv().unwrap() {
-			ThreadEvent::Quit => {
+		loop {
+			if let Some(event) = self.events.queue.borrow_mut().pop_front() {
+				return event;
+			}
+			let batch = self.next_batch();
+			self.coalesce(batch);
+		}
+	}
+	// TODO: when adding an ipc interface add its sockets here
+	/// wait for anything to happen, then take everything that's queued up
+	fn next_batch(&self) -> Vec<RawEvent> {
+		let mut batch = Vec::new();
+		loop {
+			// xcb may have already read events off the socket while waiting for
+			// replies, so they need to be drained before polling
+			loop {
+				match self.connection.poll_for_event() {
+					Err(xcb::Error::Protocol(err)) => batch.push(RawEvent::Err(err)),
+					Err(xcb::Error::Connection(err)) => {
+						// every call after this fails too, so stop here
+						batch.push(RawEvent::ConnectionLost(err));
+						return batch;
+					}
+					Ok(Some(event)) => batch.push(raw_event(event)),
+					Ok(None) => break,
+				}
+			}
+			while let Some(signal) = self.events.signals.read() {
+				batch.push(RawEvent::Signal(signal));
+			}
+			let remaining = self
+				.events
+				.interval
+				.saturating_sub(self.events.last_batch.get().elapsed());
+			// more events for the same windows will probably show up before the
+			// interval is over, but signals shouldn't have to wait for it
+			let signalled = batch
+				.iter()
+				.any(|event| matches!(event, RawEvent::Signal(_)));
+			if !batch.is_empty() && (remaining.is_zero() || signalled) {
+				self.events.last_batch.set(Instant::now());
+				return batch;
+			}
+			let timeout = if batch.is_empty() {
+				-1
+			} else {
+				i32::try_from(remaining.as_micros().div_ceil(1000)).unwrap_or(i32::MAX)
+			};
+			let mut processes = self.events.processes.borrow_mut();
+			let mut fds = [pollfd(&self.connection), pollfd(&self.events.signals.fd)]
+				.into_iter()
+				.chain(processes.iter().map(|(_, fd)| pollfd(fd)))
+				.collect::<Vec<_>>();
+			if let Err(err) = poll(&mut fds, timeout) {
+				output::error(err);
+				continue;
+			}
+			let mut exited = fds[2..].iter().map(|fd| fd.revents != 0);
+			processes.retain(|&(pid, _)| {
+				let exited = exited.next().unwrap_or_default();
+				if exited {
+					batch.push(RawEvent::ProcessExit(pid));
+				}
+				!exited
+			});
+		}
+	}
+	/// turn a batch from `next_batch` into events, so that every window is
+	/// only updated once per batch
+	fn coalesce(&self, batch: Vec<RawEvent>) {
+		let mut queue = self.events.queue.borrow_mut();
+		// index of each window's update in `queue`
+		let mut updates = HashMap::<Window, usize>::default();
+		for event in batch {
+			let event = self.convert_event(event);
+			match event {
+				Event::Interrupted => continue,
+				Event::Update(win) | Event::Configure(win, _) => {
+					if let Some(&index) = updates.get(&win) {
+						queue[index] = match (queue[index], event) {
+							(Event::Configure(_, old), Event::Configure(_, new))
+								if old.synthetic == new.synthetic =>
+							{
+								event
+							}
+							// can't merge these, so measure everything again
+							_ => Event::Update(win),
+						};
+						continue;
+					}
+					updates.insert(win, queue.len());
+				}
+				Event::Close(win) => {
+					// no point measuring a window that's gone
+					if let Some(index) = updates.remove(&win) {
+						queue[index] = Event::Interrupted;
+					}
+				}
+				_ => {}
+			}
+			queue.push_back(event);
+		}
+		queue.retain(|event| !matches!(event, Event::Interrupted));
+	}
+	fn convert_event(&self, event: RawEvent) -> Event {
+		match event {
+			RawEvent::Signal(signal) if signal.cast_signed() == libc::SIGUSR1 => Event::Dump,
+			RawEvent::Signal(_) => {
 				output::quit();
 				Event::Quit
 			}
-			ThreadEvent::Err(err) => {
-				output::error(err);
+			RawEvent::Err(err) => {
+				output::protocol_error(self.blame(&err), &err);
 				Event::Interrupted
 			}
-			ThreadEvent::PropertyNotify(event) => {
+			RawEvent::ConnectionLost(err) => {
+				output::connection_lost(&err);
+				Event::ConnectionLost
+			}
+			RawEvent::ProcessExit(pid) => Event::ProcessExit(pid),
+			RawEvent::PropertyNotify(event) => {
+				self.events.last_time.set(event.time());
 				if event.atom() == self.atom_client_list && event.window() == self.root {
 					Event::WindowList
+				} else if event.atom() == self.atom_client_list_stacking
+					&& event.window() == self.root
+				{
+					Event::Stacking
+				} else if event.atom() == self.atom_number_of_desktops
+					&& event.window() == self.root
+				{
+					output::desktops(self.update_desktops());
+					Event::Interrupted
+				} else if event.atom() == self.atom_supported && event.window() == self.root {
+					self.update_supported();
+					Event::Interrupted
 				} else if event.atom() == self.atom_desktop || event.atom() == self.atom_state {
 					Event::Update(event.window())
+				} else if event.atom() == x::ATOM_WM_HINTS {
+					Event::Hints(event.window())
+				} else {
+					Event::Interrupted
+				}
+			}
+			RawEvent::SelectionClear(event) => {
+				if event.selection() == self.selection {
+					output::replaced();
+					Event::Replaced
 				} else {
 					Event::Interrupted
 				}
 			}
-			ThreadEvent::ConfigureNotify(event) => Event::Update(event.window()),
-			ThreadEvent::DestroyNotify(event) => Event::Close(event.window()),
-			ThreadEvent::Other => Event::Interrupted,
+			RawEvent::ConfigureNotify(event) => Event::Configure(event.window(), Configure {
+				x: event.x(),
+				y: event.y(),
+				w: event.width(),
+				h: event.height(),
+				// sent by the window manager, in root coordinates (see ICCCM 4.1.5)
+				synthetic: event.response_type() & 0x80 != 0,
+			}),
+			RawEvent::DestroyNotify(event) => Event::Close(event.window()),
+			RawEvent::ScreenChange => {
+				let monitors = get_monitors(&self.connection, self.root);
+				output::monitors(monitors.len());
+				*self.monitors.borrow_mut() = monitors;
+				Event::Interrupted
+			}
+			RawEvent::Other => Event::Interrupted,
+		}
+	}
+	/// get an `Event::ProcessExit` when this process exits
+	pub fn watch_process(&self, pid: ProcessId) {
+		let Some(fd) = pid.open_pidfd() else { return };
+		self.events.processes.borrow_mut().push((pid, fd));
+	}
+	/// undoes one `watch_process`, a process can be watched more than once
+	pub fn unwatch_process(&self, pid: ProcessId) {
+		let mut processes = self.events.processes.borrow_mut();
+		if let Some(index) = processes.iter().position(|&(test, _)| test == pid) {
+			processes.swap_remove(index);
 		}
 	}
 	pub fn flush(&self) {
@@ -267,6 +1242,49 @@ impl Context {
 			event,
 		}
 	}
+	/// send a request without a reply, remembering which operation it was for
+	/// so errors about it can be blamed on that
+	fn send<R: xcb::Request<Cookie = xcb::VoidCookie>>(
+		&self,
+		operation: &'static str,
+		window: Window,
+		request: &R,
+	) {
+		let sequence = self.connection.send_request(request).sequence();
+		let mut operations = self.operations.borrow_mut();
+		if operations.len() == OPERATION_HISTORY {
+			operations.pop_front();
+		}
+		operations.push_back(Operation {
+			sequence,
+			name: operation,
+			window,
+		});
+	}
+	/// `send` a client message
+	fn send_message(&self, operation: &'static str, window: Window, atom: Atom, data32: [u32; 5]) {
+		self.send(
+			operation,
+			window,
+			&self.client_message2(&Self::client_message1(window, atom, data32)),
+		);
+	}
+	/// find the operation that caused an error
+	fn blame(&self, err: &xcb::ProtocolError) -> Option<Operation> {
+		// only core requests are sent through `send`
+		let xcb::ProtocolError::X(err, _) = err else {
+			return None;
+		};
+		let sequence = error_sequence(err);
+		// errors only have the lower 16 bits of the sequence number
+		#[expect(clippy::cast_possible_truncation, reason = "on purpose")]
+		self.operations
+			.borrow()
+			.iter()
+			.rev()
+			.find(|operation| operation.sequence as u16 == sequence)
+			.copied()
+	}
 	/// like flush, but wait for the replies
 	pub fn flush_hard(&self) {
 		output::flush_hard();
@@ -291,13 +1309,59 @@ impl Context {
 			)))
 	}
 	pub fn subscribe(&self, window: Window) {
-		self.connection.send_request(&x::ChangeWindowAttributes {
+		self.send("subscribe", window, &x::ChangeWindowAttributes {
 			window,
 			value_list: &[x::Cw::EventMask(
 				x::EventMask::PROPERTY_CHANGE | x::EventMask::STRUCTURE_NOTIFY,
 			)],
 		});
 	}
+	/// stop getting events from a window `subscribe` was used on
+	pub fn unsubscribe(&self, window: Window) {
+		self.send("unsubscribe", window, &x::ChangeWindowAttributes {
+			window,
+			value_list: &[x::Cw::EventMask(x::EventMask::NO_EVENT)],
+		});
+	}
+	/// urgency hint from `WM_HINTS`, or `_NET_WM_STATE_DEMANDS_ATTENTION`
+	pub fn is_urgent(&self, window: Window) -> bool {
+		// from ICCCM 4.1.2.4
+		const URGENCY_HINT: u32 = 256;
+		let hints = self.connection.send_request(&Self::get_property(
+			window,
+			x::ATOM_WM_HINTS,
+			x::ATOM_WM_HINTS,
+			1,
+		));
+		let state = self.connection.send_request(&Self::get_property(
+			window,
+			self.atom_state,
+			x::ATOM_ATOM,
+			u32::MAX,
+		));
+		let hints = self.connection.wait_for_reply(hints);
+		let state = self.connection.wait_for_reply(state);
+		hints.is_ok_and(|hints| {
+			hints
+				.value::<u32>()
+				.first()
+				.is_some_and(|flags| flags & URGENCY_HINT != 0)
+		}) || state.is_ok_and(|state| {
+			state
+				.value::<Atom>()
+				.contains(&self.atom_state_demands_attention)
+		})
+	}
+	pub fn set_demands_attention(&self, window: Window, urgent: bool) {
+		output::window_urgent(window, urgent);
+		self.send_message("set_demands_attention", window, self.atom_state, [
+			urgent.into(),
+			self.atom_state_demands_attention.resource_id(),
+			0,
+			2,
+			0,
+		]);
+	}
 	pub fn get_window_geometry(&self, window: Window) -> Option<Geometry> {
 		// seems weird that i get window position like this
 		let position = self.connection.send_request(&x::TranslateCoordinates {
@@ -316,71 +1380,410 @@ impl Context {
 			x::ATOM_CARDINAL,
 			4,
 		));
-		#[expect(
-			clippy::cast_possible_truncation,
-			reason = "4 gigabyte atom? in this economy?"
-		)]
+		let extents = self.connection.send_request(&Self::get_property(
+			window,
+			self.atom_frame_extents,
+			x::ATOM_CARDINAL,
+			4,
+		));
 		let state = self.connection.send_request(&Self::get_property(
 			window,
 			self.atom_state,
 			x::ATOM_ATOM,
-			12 * size_of::<Atom>() as u32,
+			u32::MAX,
 		));
+		let tree = self.connection.send_request(&x::QueryTree { window });
 		// all requests sent in parallel
 		let position = self.connection.wait_for_reply(position).ok()?;
 		let size = self.connection.wait_for_reply(size).ok()?;
 		let desktop = self.connection.wait_for_reply(desktop).ok()?;
 		let state = self.connection.wait_for_reply(state).ok()?;
-		Some(Geometry {
-			x: position.dst_x() - size.x(),
-			y: position.dst_y() - size.y(),
+		let extents = self.connection.wait_for_reply(extents).ok()?;
+		let tree = self.connection.wait_for_reply(tree).ok()?;
+		// left, right, top, bottom
+		let (fx, fy, fw, fh) = if let &[left, right, top, bottom] = extents.value::<u32>() {
+			(
+				i16::try_from(left).unwrap_or_default(),
+				i16::try_from(top).unwrap_or_default(),
+				u16::try_from(left + right).unwrap_or_default(),
+				u16::try_from(top + bottom).unwrap_or_default(),
+			)
+		} else {
+			// guess from where it's been reparented, `request_frame_extents`
+			// should make it be there next time, with the bottom border as wide
+			// as the sides
+			let (left, top) = (
+				size.x().max(0).cast_unsigned(),
+				size.y().max(0).cast_unsigned(),
+			);
+			(size.x(), size.y(), left * 2, top + left)
+		};
+		Some(self.locate(Geometry {
+			x: position.dst_x() - fx,
+			y: position.dst_y() - fy,
 			w: size.width(),
 			h: size.height(),
 			d: desktop.value().first().copied().unwrap_or_default(),
 			s: WindowState::new(self, state.value::<Atom>()),
-		})
+			managed: WindowState::DEFAULT,
+			fx,
+			fy,
+			fw,
+			fh,
+			framed: tree.parent() != self.root,
+			m: None,
+		}))
+	}
+	/// ask the window manager to set `_NET_FRAME_EXTENTS`, once per window
+	/// since some only do it when asked
+	pub fn request_frame_extents(&self, window: Window) {
+		if self.wm_supports(self.atom_request_frame_extents) {
+			self.send_message(
+				"request_frame_extents",
+				window,
+				self.atom_request_frame_extents,
+				[0; 5],
+			);
+		}
+	}
+	/// whether the window manager lists `atom` in `_NET_SUPPORTED`
+	fn wm_supports(&self, atom: Atom) -> bool {
+		self.supported.borrow().contains(&atom)
+	}
+	fn update_supported(&self) {
+		let supported = self
+			.connection
+			.wait_for_reply(self.connection.send_request(&Self::get_property(
+				self.root,
+				self.atom_supported,
+				x::ATOM_ATOM,
+				u32::MAX,
+			)))
+			.map(|reply| reply.value().to_vec())
+			.unwrap_or_default();
+		*self.supported.borrow_mut() = supported;
+	}
+	fn get_size_hints(&self, window: Window) -> SizeHints {
+		self.connection
+			.wait_for_reply(self.connection.send_request(&Self::get_property(
+				window,
+				x::ATOM_WM_NORMAL_HINTS,
+				x::ATOM_WM_SIZE_HINTS,
+				18,
+			)))
+			.map(|reply| SizeHints::from_cardinals(reply.value()))
+			.unwrap_or_default()
+	}
+	fn update_desktops(&self) -> u32 {
+		let desktops = self
+			.connection
+			.wait_for_reply(self.connection.send_request(&Self::get_property(
+				self.root,
+				self.atom_number_of_desktops,
+				x::ATOM_CARDINAL,
+				1,
+			)))
+			.ok()
+			.and_then(|reply| reply.value::<u32>().first().copied())
+			.unwrap_or_default();
+		self.desktops.set(desktops);
+		desktops
+	}
+	/// the last desktop if `desktop` was removed since it got measured
+	fn existing_desktop(&self, desktop: u32) -> u32 {
+		let count = self.desktops.get();
+		if desktop == ALL_DESKTOPS || desktop < count || count == 0 {
+			desktop
+		} else {
+			count - 1
+		}
 	}
+	/// record which monitor a geometry is on (by its centre)
+	pub fn locate(&self, mut geometry: Geometry) -> Geometry {
+		let center = geometry.center_point();
+		geometry.m = self
+			.monitors
+			.borrow()
+			.iter()
+			.copied()
+			.find(|monitor| monitor.contains(center));
+		geometry
+	}
+	/// move a geometry along with its monitor, or onto the primary monitor
+	/// if its own is gone
+	fn remap(&self, mut geometry: Geometry) -> Geometry {
+		let monitors = self.monitors.borrow();
+		let Some(old) = geometry.m else {
+			return geometry;
+		};
+		let Some(&new) = monitors
+			.iter()
+			.find(|monitor| monitor.name == old.name)
+			.or(monitors.first())
+		else {
+			return geometry;
+		};
+		if new != old {
+			geometry.x = remap_span(geometry.x, geometry.w, (old.x, old.w), (new.x, new.w));
+			geometry.y = remap_span(geometry.y, geometry.h, (old.y, old.h), (new.y, new.h));
+			geometry.m = Some(new);
+		}
+		geometry
+	}
+	/// the window gets as big as it's willing to inside of `geometry`'s area,
+	/// and is centred in any leftover space (e.g. an aspect-locked video)
 	pub fn set_window_geometry(&self, window: Window, geometry: Geometry) {
+		let mut geometry = self.remap(geometry);
+		let (w, h) = self.get_size_hints(window).fit(geometry.w, geometry.h);
+		geometry.x = center(geometry.x, geometry.w, w);
+		geometry.y = center(geometry.y, geometry.h, h);
+		(geometry.w, geometry.h) = (w, h);
 		output::window_move(window, geometry);
-		self.connection.send_request(&x::ConfigureWindow {
-			window,
-			value_list: &[
-				x::ConfigWindow::X(geometry.x.into()),
-				x::ConfigWindow::Y(geometry.y.into()),
-				x::ConfigWindow::Width(geometry.w.into()),
-				x::ConfigWindow::Height(geometry.h.into()),
-			],
-		});
-		self.connection
-			.send_request(&self.client_message2(&Self::client_message1(
+		if self.wm_supports(self.atom_moveresize_window) {
+			// north-west gravity, so x/y are the frame's corner like in
+			// `get_window_geometry`, and every field set, from a pager
+			const FLAGS: u32 = 1 | 0xF << 8 | 2 << 12;
+			self.send_message(
+				"set_window_geometry",
 				window,
-				self.atom_desktop,
-				[geometry.d, 2, 0, 0, 0],
-			)));
-		for event in geometry.s.events(self) {
-			self.connection
-				.send_request(&self.client_message2(&Self::client_message1(
-					window,
-					self.atom_state,
-					event,
-				)));
+				self.atom_moveresize_window,
+				[
+					FLAGS,
+					i32::from(geometry.x).cast_unsigned(),
+					i32::from(geometry.y).cast_unsigned(),
+					geometry.w.into(),
+					geometry.h.into(),
+				],
+			);
+		} else {
+			// up to the window manager how this lines up with the frame
+			self.send("set_window_geometry", window, &x::ConfigureWindow {
+				window,
+				value_list: &[
+					x::ConfigWindow::X(geometry.x.into()),
+					x::ConfigWindow::Y(geometry.y.into()),
+					x::ConfigWindow::Width(geometry.w.into()),
+					x::ConfigWindow::Height(geometry.h.into()),
+				],
+			});
+		}
+		self.send_message("set_window_geometry", window, self.atom_desktop, [
+			self.existing_desktop(geometry.d),
+			2,
+			0,
+			0,
+			0,
+		]);
+		for event in geometry.s.events(self, geometry.managed) {
+			self.send_message("set_window_geometry", window, self.atom_state, event);
 		}
 		let change_state = if geometry.s.is_hidden() { 3 } else { 1 };
+		self.send_message("set_window_geometry", window, self.atom_change_state, [
+			change_state,
+			0,
+			0,
+			0,
+			0,
+		]);
+	}
+	/// record the list of hidden parents on the root window
+	pub fn save_parent_list(&self, parents: &[Window]) {
+		self.send("save_parent_list", self.root, &x::ChangeProperty {
+			mode: x::PropMode::Replace,
+			window: self.root,
+			property: self.atom_saved_parents,
+			r#type: x::ATOM_WINDOW,
+			data: parents,
+		});
+	}
+	pub fn load_parent_list(&self) -> Vec<Window> {
 		self.connection
-			.send_request(&self.client_message2(&Self::client_message1(
-				window,
-				self.atom_change_state,
-				[change_state, 0, 0, 0, 0],
-			)));
+			.wait_for_reply(self.connection.send_request(&Self::get_property(
+				self.root,
+				self.atom_saved_parents,
+				x::ATOM_WINDOW,
+				u32::MAX,
+			)))
+			.map(|reply| reply.value().to_vec())
+			.unwrap_or_default()
+	}
+	/// record where a parent was before it got hidden, and how it got hidden
+	pub fn save_parent(&self, parent: Window, geometry: Geometry, hide: HideStrategy) {
+		self.send("save_parent", parent, &x::ChangeProperty {
+			mode: x::PropMode::Replace,
+			window: parent,
+			property: self.atom_saved_geometry,
+			r#type: x::ATOM_CARDINAL,
+			data: &geometry.to_cardinals(),
+		});
+		self.send("save_parent", parent, &x::ChangeProperty {
+			mode: x::PropMode::Replace,
+			window: parent,
+			property: self.atom_saved_hide,
+			r#type: x::ATOM_CARDINAL,
+			data: &hide.to_cardinals(),
+		});
+	}
+	pub fn load_parent(&self, parent: Window) -> Option<(Geometry, HideStrategy)> {
+		let geometry = self.connection.send_request(&Self::get_property(
+			parent,
+			self.atom_saved_geometry,
+			x::ATOM_CARDINAL,
+			11,
+		));
+		let hide = self.connection.send_request(&Self::get_property(
+			parent,
+			self.atom_saved_hide,
+			x::ATOM_CARDINAL,
+			2,
+		));
+		let geometry = self.connection.wait_for_reply(geometry).ok()?;
+		let hide = self.connection.wait_for_reply(hide).ok()?;
+		Some((
+			Geometry::from_cardinals(geometry.value())?,
+			// saved by a version that could only unmap
+			HideStrategy::from_cardinals(hide.value()).unwrap_or(HideStrategy::Unmap),
+		))
+	}
+	/// record which parent a child swallowed
+	pub fn save_child(&self, child: Window, parent: Window) {
+		self.send("save_child", child, &x::ChangeProperty {
+			mode: x::PropMode::Replace,
+			window: child,
+			property: self.atom_saved_parent,
+			r#type: x::ATOM_WINDOW,
+			data: &[parent],
+		});
+	}
+	pub fn load_child(&self, child: Window) -> Option<Window> {
+		self.connection
+			.wait_for_reply(self.connection.send_request(&Self::get_property(
+				child,
+				self.atom_saved_parent,
+				x::ATOM_WINDOW,
+				1,
+			)))
+			.ok()?
+			.value()
+			.first()
+			.copied()
+	}
+	/// remove what `save_parent` put on a window, a window can be a child at
+	/// the same time when nesting
+	pub fn forget_parent(&self, parent: Window) {
+		for property in [self.atom_saved_geometry, self.atom_saved_hide] {
+			self.send("forget_parent", parent, &x::DeleteProperty {
+				window: parent,
+				property,
+			});
+		}
+	}
+	/// remove what `save_child` put on a window
+	pub fn forget_child(&self, child: Window) {
+		self.send("forget_child", child, &x::DeleteProperty {
+			window: child,
+			property: self.atom_saved_parent,
+		});
 	}
-	pub fn show_window(&self, window: Window) {
+	/// undo `hide_window`, call `set_window_geometry` around this
+	/// since that's what puts back the desktop and position
+	pub fn show_window(&self, window: Window, hide: HideStrategy) {
 		output::window_vis(true, window);
-		self.connection.send_request(&x::MapWindow { window });
+		match hide {
+			// mapping an iconic window also de-iconifies it
+			HideStrategy::Unmap | HideStrategy::Iconify => {
+				self.send("show_window", window, &x::MapWindow { window });
+			}
+			HideStrategy::Desktop(_) | HideStrategy::Offscreen => {}
+		}
 	}
-	/// probably resets the window's position
-	pub fn hide_window(&self, window: Window) {
+	pub fn hide_window(&self, window: Window, hide: HideStrategy) {
 		output::window_vis(false, window);
-		self.connection.send_request(&x::UnmapWindow { window });
+		match hide {
+			// probably resets the window's position
+			HideStrategy::Unmap => {
+				self.send("hide_window", window, &x::UnmapWindow { window });
+			}
+			HideStrategy::Iconify => {
+				self.send_message("hide_window", window, self.atom_change_state, [
+					3, 0, 0, 0, 0,
+				]);
+			}
+			HideStrategy::Desktop(desktop) => {
+				// a desktop that doesn't exist might leave it visible
+				self.send_message("hide_window", window, self.atom_desktop, [
+					self.existing_desktop(desktop),
+					2,
+					0,
+					0,
+					0,
+				]);
+			}
+			HideStrategy::Offscreen => {
+				// as far as the 16-bit coordinates go
+				self.send("hide_window", window, &x::ConfigureWindow {
+					window,
+					value_list: &[
+						x::ConfigWindow::X(i16::MIN.into()),
+						x::ConfigWindow::Y(i16::MIN.into()),
+					],
+				});
+			}
+		}
+	}
+	/// politely ask a window to close
+	pub fn close_window(&self, window: Window) {
+		output::window_close(window);
+		self.send_message("close_window", window, self.atom_close_window, [
+			x::CURRENT_TIME,
+			2,
+			0,
+			0,
+			0,
+		]);
+	}
+	/// windows from bottom to top
+	pub fn get_stacking(&self) -> Vec<Window> {
+		self.connection
+			.wait_for_reply(self.connection.send_request(&Self::get_property(
+				self.root,
+				self.atom_client_list_stacking,
+				x::ATOM_WINDOW,
+				u32::MAX,
+			)))
+			.map(|reply| reply.value().to_vec())
+			.unwrap_or_default()
+	}
+	/// put a window right above or below `sibling`,
+	/// or at the very top or bottom without one
+	pub fn restack_window(&self, window: Window, sibling: Option<Window>, mode: x::StackMode) {
+		output::window_restack(window, sibling, mode);
+		self.send_message("restack_window", window, self.atom_restack_window, [
+			2,
+			sibling.map_or(0, |sibling| sibling.resource_id()),
+			mode as u32,
+			0,
+			0,
+		]);
+	}
+	/// focus a window, pretending the user just interacted with it
+	/// so focus stealing prevention lets it through
+	pub fn activate_window(&self, window: Window) {
+		let time = self.events.last_time.get();
+		output::window_activate(window);
+		// 0 would mean the window never wants focus
+		if time != x::CURRENT_TIME {
+			self.send("activate_window", window, &x::ChangeProperty {
+				mode: x::PropMode::Replace,
+				window,
+				property: self.atom_user_time,
+				r#type: x::ATOM_CARDINAL,
+				data: &[time],
+			});
+		}
+		self.send_message("activate_window", window, self.atom_active_window, [
+			2, time, 0, 0, 0,
+		]);
 	}
 	/// move the focus to a window if the focus is on a previous window
 	/// (to prevent stealing the focus)
@@ -398,12 +1801,7 @@ impl Context {
 			.first()?;
 		if active == check {
 			output::window_refocus(check, window);
-			self.connection
-				.send_request(&self.client_message2(&Self::client_message1(
-					window,
-					self.atom_active_window,
-					[2, 0, 0, 0, 0],
-				)));
+			self.activate_window(window);
 		}
 		None
 	}
@@ -424,6 +1822,21 @@ impl Context {
 	pub fn window_pid(&self, window: Window) -> Option<u32> {
 		self.window_pid_reply(self.window_pid_request(window))
 	}
+	/// startup notification id, for windows that were launched by another
+	/// process (e.g. `xdg-open` forwarding to an already-running program)
+	pub fn window_startup_id(&self, window: Window) -> Option<Vec<u8>> {
+		let reply = self
+			.connection
+			.wait_for_reply(self.connection.send_request(&Self::get_property(
+				window,
+				self.atom_startup_id,
+				self.atom_utf8_string,
+				u32::MAX,
+			)))
+			.ok()?;
+		let value = reply.value::<u8>();
+		(!value.is_empty()).then(|| value.to_vec())
+	}
 
 	/// fallback checks probably too many windows,
 	/// but it's a rare* enough case that it probably won't hurt
@@ -449,16 +1862,24 @@ impl Context {
 			Some(window)
 		})
 	}
-	/// this is only used for terminals so it should be fine
-	/// to assume one window per pid, not sure how swallowing
-	/// a multi-window application would work anyways
-	pub fn find_window_with_pid(&self, pid: u32, window_list: &[Window]) -> Option<Window> {
+	/// pid of every window that has one, requesting them all at once
+	pub fn window_pids(&self, windows: &[Window]) -> Vec<(Window, u32)> {
+		let requests = windows
+			.iter()
+			.map(|&window| (window, self.window_pid_request(window)))
+			.collect::<Vec<_>>();
+		requests
+			.into_iter()
+			.filter_map(|(window, request)| Some((window, self.window_pid_reply(request)?)))
+			.collect()
+	}
+	/// slow path for windows that aren't in the client list,
+	/// walks the whole tree until something has the right pid
+	pub fn find_window_in_tree(&self, pid: u32) -> Option<Window> {
 		const PARALLEL_REQUESTS: usize = 10;
 		let mut queue = VecDeque::with_capacity(PARALLEL_REQUESTS);
-		let mut requests = window_list
-			.iter()
-			.copied()
-			.chain(self.fallback_iter())
+		let mut requests = self
+			.fallback_iter()
 			.map(|window| (window, self.window_pid_request(window)));
 		// start multiple requests in parallel
 		iter::from_fn(move || {
@@ -471,30 +1892,213 @@ impl Context {
 	}
 }
 
-// [porting] use whatever sane-ish api exists, it's probably better than this
-/// programmer rolls “worst parser ever”, asked to leave /proc/{pid}/status
-pub fn get_pid_info(pid: u32) -> Option<(u32, Vec<u8>)> {
-	let mut file = BufReader::new(File::open(format!("/proc/{pid}/status")).ok()?);
-	let (mut ppid, mut name, mut line) = (None, None, Vec::new());
-	while ppid.is_none() || name.is_none() {
-		line.clear();
-		file.read_until(b'\n', &mut line).ok()?;
-		line.pop();
-		if let Some(ppid_text) = line.strip_prefix(b"PPid:\t") {
-			ppid = Some(from_utf8(ppid_text).ok()?.parse().ok()?);
-		} else if let Some(name_text) = line.strip_prefix(b"Name:\t") {
-			#[cfg_attr(rust_analyzer, expect(unused_mut, reason = "rust-analyzer#18209"))]
-			let mut normal = true;
-			let iter = name_text.iter().filter_map(|&(mut ch)| {
-				(normal, ch) = match ch {
-					b'\\' if normal => (false, ch),
-					b'n' if !normal => (true, b'\n'),
-					_ => (true, ch),
-				};
-				normal.then_some(ch)
-			});
-			name = Some(iter.collect());
+#[cfg(test)]
+mod tests {
+	use proptest::prelude::*;
+
+	use super::{Geometry, Layout, SizeHints, WindowState, remap_span, split};
+
+	fn geometry(x: i16, y: i16, w: u16, h: u16) -> Geometry {
+		Geometry {
+			x,
+			y,
+			w,
+			h,
+			d: 0,
+			s: WindowState(0),
+			managed: WindowState::DEFAULT,
+			fx: 0,
+			fy: 0,
+			fw: 0,
+			fh: 0,
+			framed: false,
+			m: None,
+		}
+	}
+
+	/// every tile of a layout, as `(x, y, w, h)`, without frames
+	fn tiles(layout: Layout, count: usize) -> Option<Vec<(i16, i16, u16, u16)>> {
+		(0..count)
+			.map(|index| {
+				let tile = geometry(0, 0, 100, 50).tile(layout, index, count)?;
+				Some((tile.x, tile.y, tile.w, tile.h))
+			})
+			.collect()
+	}
+
+	#[test]
+	fn fit_without_hints() {
+		assert_eq!(SizeHints::default().fit(100, 50), (100, 50));
+	}
+
+	#[test]
+	fn fit_min_and_max() {
+		let hints = SizeHints {
+			min: Some((200, 10)),
+			max: Some((300, 40)),
+			..SizeHints::default()
+		};
+		// the minimum wins over the space that's available
+		assert_eq!(hints.fit(100, 50), (200, 40));
+	}
+
+	#[test]
+	fn fit_increments() {
+		let hints = SizeHints {
+			increment: Some((7, 5)),
+			base: Some((10, 10)),
+			..SizeHints::default()
+		};
+		assert_eq!(hints.fit(100, 50), (94, 50));
+		// the minimum size stands in for a missing base size
+		let hints = SizeHints {
+			increment: Some((10, 10)),
+			min: Some((4, 4)),
+			..SizeHints::default()
+		};
+		assert_eq!(hints.fit(100, 50), (94, 44));
+		let hints = SizeHints {
+			increment: Some((10, 10)),
+			..SizeHints::default()
+		};
+		assert_eq!(hints.fit(105, 47), (100, 40));
+		// zero increments would divide by zero
+		let hints = SizeHints {
+			increment: Some((0, 0)),
+			..SizeHints::default()
+		};
+		assert_eq!(hints.fit(105, 47), (105, 47));
+	}
+
+	#[test]
+	fn fit_aspect() {
+		let square = SizeHints {
+			aspect: Some(((1, 1), (1, 1))),
+			..SizeHints::default()
+		};
+		assert_eq!(square.fit(200, 100), (100, 100));
+		assert_eq!(square.fit(100, 200), (100, 100));
+		// the base size isn't part of the ratio
+		let based = SizeHints {
+			base: Some((20, 0)),
+			..square
+		};
+		assert_eq!(based.fit(220, 100), (120, 100));
+		// limits of 0 mean there's no limit
+		let unlimited = SizeHints {
+			aspect: Some(((0, 0), (0, 0))),
+			..SizeHints::default()
+		};
+		assert_eq!(unlimited.fit(100, 50), (100, 50));
+	}
+
+	#[test]
+	fn remap_span_moves_along() {
+		assert_eq!(remap_span(10, 50, (0, 100), (0, 100)), 10);
+		assert_eq!(remap_span(100, 200, (0, 1920), (1920, 1280)), 2020);
+		assert_eq!(remap_span(-1000, 200, (-1920, 1920), (0, 1920)), 920);
+	}
+
+	#[test]
+	fn remap_span_stays_inside() {
+		// past the end of a smaller monitor
+		assert_eq!(remap_span(1800, 400, (0, 1920), (0, 1280)), 880);
+		// too big to fit, so it sticks to the start
+		assert_eq!(remap_span(100, 2000, (0, 1920), (0, 1280)), 0);
+	}
+
+	#[test]
+	fn split_uneven() {
+		assert_eq!(split(0, 100, 0, 3), (0, 33));
+		assert_eq!(split(0, 100, 1, 3), (33, 33));
+		assert_eq!(split(0, 100, 2, 3), (66, 34));
+		assert_eq!(split(-50, 100, 1, 2), (0, 50));
+	}
+
+	#[test]
+	fn tile_layouts() {
+		assert_eq!(tiles(Layout::None, 2), None);
+		assert_eq!(
+			tiles(Layout::Horizontal, 2),
+			Some(vec![(0, 0, 50, 50), (50, 0, 50, 50)])
+		);
+		assert_eq!(
+			tiles(Layout::Vertical, 2),
+			Some(vec![(0, 0, 100, 25), (0, 25, 100, 25)])
+		);
+		assert_eq!(tiles(Layout::Grid, 1), Some(vec![(0, 0, 100, 50)]));
+		assert_eq!(
+			tiles(Layout::Grid, 4),
+			Some(vec![
+				(0, 0, 50, 25),
+				(50, 0, 50, 25),
+				(0, 25, 50, 25),
+				(50, 25, 50, 25)
+			])
+		);
+	}
+
+	#[test]
+	fn tile_uneven_grid() {
+		// the last row is wider
+		assert_eq!(
+			tiles(Layout::Grid, 3),
+			Some(vec![(0, 0, 50, 25), (50, 0, 50, 25), (0, 25, 100, 25)])
+		);
+		assert_eq!(
+			tiles(Layout::Grid, 5),
+			Some(vec![
+				(0, 0, 33, 25),
+				(33, 0, 33, 25),
+				(66, 0, 34, 25),
+				(0, 25, 50, 25),
+				(50, 25, 50, 25)
+			])
+		);
+	}
+
+	#[test]
+	fn tile_unfills() {
+		let filling = WindowState::FILLING.union(WindowState::from_name(b"sticky").unwrap());
+		let tile = Geometry {
+			s: filling,
+			..geometry(0, 0, 100, 50)
+		}
+		.tile(Layout::Horizontal, 0, 2)
+		.unwrap();
+		assert_eq!(tile.s, WindowState::from_name(b"sticky").unwrap());
+	}
+
+	#[test]
+	fn tile_frames() {
+		let parent = Geometry {
+			fw: 10,
+			fh: 30,
+			..geometry(0, 0, 90, 70)
+		};
+		// the frames are 50×50 each, the windows inside them smaller
+		let tile = parent.tile(Layout::Grid, 3, 4).unwrap();
+		assert_eq!((tile.x, tile.y, tile.w, tile.h), (50, 50, 40, 20));
+		let child = Geometry {
+			fw: 4,
+			fh: 20,
+			..geometry(0, 0, 1, 1)
+		}
+		.fit_frame(tile);
+		assert_eq!((child.w, child.h), (46, 30));
+	}
+
+	proptest! {
+		#[test]
+		fn split_covers_span(start in any::<i16>(), size in any::<u16>(), count in 1..16_usize) {
+			let parts = (0..count)
+				.map(|index| split(start, size, index, count))
+				.collect::<Vec<_>>();
+			prop_assert_eq!(parts.iter().map(|&(_, size)| u32::from(size)).sum::<u32>(), u32::from(size));
+			for pair in parts.windows(2) {
+				let ((first, first_size), (second, _)) = (pair[0], pair[1]);
+				prop_assert_eq!(first.saturating_add_unsigned(first_size), second);
+			}
 		}
 	}
-	Some((ppid.unwrap(), name.unwrap()))
 }
@@ -0,0 +1,94 @@
+//! window list diffing, in its own module so the benchmarks can use it
+use std::hash::Hash;
+
+use foldhash::HashSet;
+
+/// update `target` to `source`, returning what was added and removed.
+/// linear no matter how the list got reordered, since not every window
+/// manager keeps `_NET_CLIENT_LIST` in mapping order.
+/// added items are in `source` order, removed ones in `target` order
+pub fn list_diff<T: Eq + Hash + Copy>(target: &mut Vec<T>, source: &[T]) -> (Vec<T>, Vec<T>) {
+	let old = target.iter().copied().collect::<HashSet<_>>();
+	let new = source.iter().copied().collect::<HashSet<_>>();
+	let added = source
+		.iter()
+		.copied()
+		.filter(|val| !old.contains(val))
+		.collect();
+	let removed = target
+		.iter()
+		.copied()
+		.filter(|val| !new.contains(val))
+		.collect();
+	target.clear();
+	target.extend_from_slice(source);
+	(added, removed)
+}
+
+#[cfg(test)]
+mod tests {
+	use foldhash::HashSet;
+	use proptest::prelude::*;
+
+	use super::list_diff;
+
+	/// the previous implementation, only fast when the prefix stays the same
+	fn reference<T: Eq + Copy>(target: &mut Vec<T>, source: &[T], mut call: impl FnMut(T)) {
+		for (i, val) in source.iter().enumerate() {
+			let j = target
+				.iter()
+				.enumerate()
+				.skip(i)
+				.find_map(|(j, test)| (test == val).then_some(j))
+				.unwrap_or_else(|| {
+					call(*val);
+					target.push(*val);
+					target.len() - 1
+				});
+			target.swap(i, j);
+		}
+		target.truncate(source.len());
+	}
+
+	/// window lists don't have duplicates
+	fn window_list() -> impl Strategy<Value = Vec<u32>> {
+		prop::collection::vec(0..64_u32, 0..48).prop_map(|mut list| {
+			let mut seen = HashSet::default();
+			list.retain(|&val| seen.insert(val));
+			list
+		})
+	}
+
+	proptest! {
+		#[test]
+		fn matches_reference(target in window_list(), source in window_list()) {
+			let mut reference_target = target.clone();
+			let mut reference_added = Vec::new();
+			reference(&mut reference_target, &source, |val| reference_added.push(val));
+			let mut new_target = target.clone();
+			let (added, removed) = list_diff(&mut new_target, &source);
+			prop_assert_eq!(&new_target, &reference_target);
+			prop_assert_eq!(added, reference_added);
+			let expected_removed = target
+				.iter()
+				.copied()
+				.filter(|val| !source.contains(val))
+				.collect::<Vec<_>>();
+			prop_assert_eq!(removed, expected_removed);
+		}
+
+		#[test]
+		fn reordering_changes_nothing(
+			target in window_list().prop_shuffle(),
+			seed in any::<u64>(),
+		) {
+			let mut source = target.clone();
+			source.rotate_left(usize::try_from(seed).unwrap_or_default() % target.len().max(1));
+			let mut new_target = target.clone();
+			let (added, removed) = list_diff(&mut new_target, &source);
+			prop_assert_eq!(new_target, source);
+			prop_assert!(added.is_empty());
+			prop_assert!(removed.is_empty());
+		}
+	}
+}
@@ -1,27 +1,114 @@
 //! This is a binary crate
 
+use std::env::args;
+use std::process::ExitCode;
+use std::thread;
+use std::time::Duration;
+
 use context::{Context, Event};
 use swallow::Swallow;
 
+mod config;
 mod context;
+mod diff;
 mod output;
+mod process;
 mod swallow;
 
-fn main() -> xcb::Result<()> {
-	output::welcome();
-	let cx = Context::new()?;
+const RECONNECT_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_MAX: Duration = Duration::from_secs(30);
+
+/// command-line flags
+#[derive(Default)]
+#[expect(clippy::struct_excessive_bools, reason = "they're independent flags")]
+struct Args {
+	adopt_existing: bool,
+	keep_hidden: bool,
+	replace: bool,
+	reconnect: bool,
+	/// minimum time between event batches
+	update_interval: Duration,
+}
+
+impl Args {
+	fn parse() -> Option<Self> {
+		let mut out = Self::default();
+		for arg in args().skip(1) {
+			match arg.as_str() {
+				"--adopt-existing" => out.adopt_existing = true,
+				"--keep-hidden" => out.keep_hidden = true,
+				"--replace" => out.replace = true,
+				"--reconnect" => out.reconnect = true,
+				_ => {
+					let millis = arg.strip_prefix("--update-interval=")?;
+					out.update_interval = Duration::from_millis(millis.parse().ok()?);
+				}
+			}
+		}
+		Some(out)
+	}
+}
+
+/// why `run` stopped
+enum Exit {
+	/// nothing more to do
+	Done(ExitCode),
+	/// connected at first, but then lost the connection
+	ConnectionLost,
+}
+
+fn run(args: &Args) -> xcb::Result<Exit> {
+	let Some(cx) = Context::new(args.replace, args.update_interval)? else {
+		return Ok(Exit::Done(ExitCode::FAILURE));
+	};
 	let mut swallow = Swallow::new(&cx)?;
+	if args.adopt_existing {
+		swallow.adopt_existing(&cx);
+	}
 	loop {
 		match cx.next_event() {
 			Event::Interrupted => None,
 			Event::Quit => {
-				swallow.quit(&cx);
-				break;
+				swallow.quit(&cx, !args.keep_hidden);
+				return Ok(Exit::Done(ExitCode::SUCCESS));
 			}
+			Event::Replaced => {
+				// the next instance picks up the hidden windows
+				swallow.quit(&cx, false);
+				return Ok(Exit::Done(ExitCode::SUCCESS));
+			}
+			// all the windows are gone with it, so there's nothing to clean up
+			Event::ConnectionLost => return Ok(Exit::ConnectionLost),
+			Event::Dump => swallow.dump(),
 			Event::WindowList => swallow.window_list(&cx),
+			Event::Stacking => swallow.stacking(&cx),
 			Event::Update(win) => swallow.update(&cx, win),
+			Event::Hints(win) => swallow.hints(&cx, win),
+			Event::Configure(win, configure) => swallow.configure(&cx, win, configure),
 			Event::Close(win) => swallow.close(&cx, win),
+			Event::ProcessExit(pid) => swallow.process_exit(&cx, pid),
 		};
 	}
-	Ok(())
+}
+
+fn main() -> ExitCode {
+	output::welcome();
+	let Some(args) = Args::parse() else {
+		output::usage();
+		return ExitCode::FAILURE;
+	};
+	let mut delay = RECONNECT_MIN;
+	loop {
+		match run(&args) {
+			Ok(Exit::Done(code)) => return code,
+			Ok(Exit::ConnectionLost) => delay = RECONNECT_MIN,
+			Err(err) => output::error(err),
+		}
+		if !args.reconnect {
+			return ExitCode::FAILURE;
+		}
+		output::reconnecting(delay);
+		thread::sleep(delay);
+		delay = (delay * 2).min(RECONNECT_MAX);
+	}
 }
@@ -3,12 +3,13 @@
 use std::error::Error;
 use std::fmt;
 use std::rc::Rc;
+use std::time::Duration;
 
 use foldhash::HashSet;
 use xcb::Xid;
-use xcb::x::{Atom, Window};
+use xcb::x::{Atom, StackMode, Window};
 
-use crate::context::Geometry;
+use crate::context::{Geometry, Operation};
 
 /// for better-looking outputs,
 /// doesn't really matter since it's logs but i like nice logs
@@ -73,20 +74,81 @@ pub fn quit() {
 	eprintln!("Quitting…");
 }
 
+pub fn already_running(owner: Window) {
+	eprintln!(
+		"Already running as {}, use --replace to take over",
+		MiniDebug(owner)
+	);
+}
+
+pub fn replacing(owner: Window) {
+	eprintln!("Replacing {}", MiniDebug(owner));
+}
+
+pub fn replace_timeout(owner: Window) {
+	eprintln!(
+		"{} didn't quit in time, continuing anyways",
+		MiniDebug(owner)
+	);
+}
+
+pub fn replaced() {
+	eprintln!("Replaced by another instance, quitting…");
+}
+
 pub fn error<E: Error>(e: E) {
 	eprintln!("Error {e}\n{e:?}");
 }
 
+pub fn protocol_error(operation: Option<Operation>, err: &xcb::ProtocolError) {
+	match operation {
+		Some(Operation { name, window, .. }) => {
+			eprintln!("Error in {} on {}: {:?}", name, MiniDebug(window), err);
+		}
+		None => eprintln!("Error {err:?}"),
+	}
+}
+
+pub fn connection_lost(err: &xcb::ConnError) {
+	eprintln!("Lost connection to X: {err}");
+}
+
+pub fn reconnecting(delay: Duration) {
+	eprintln!("Reconnecting in {}s…", delay.as_secs());
+}
+
 pub fn setup_context(screen: i32, window: Window, atoms: &[Atom]) {
 	eprintln!("Root: {} / {}", screen, MiniDebug(window));
 	eprintln!("Atoms: {}", MiniDebug(atoms));
 }
 
-pub fn setup_state(immune: &HashSet<Rc<[u8]>>, terminal: &HashSet<Rc<[u8]>>) {
-	eprintln!("Terminal processes: {}", MiniDebug(terminal));
+pub fn monitors(count: usize) {
+	eprintln!("Monitors changed: {count}");
+}
+
+pub fn desktops(count: u32) {
+	eprintln!("Desktops changed: {count}");
+}
+
+pub fn setup_state(immune: &HashSet<Rc<[u8]>>, parent: &HashSet<Rc<[u8]>>) {
+	eprintln!("Parent processes: {}", MiniDebug(parent));
 	eprintln!("Immune processes: {}", MiniDebug(immune));
 }
 
+pub fn usage() {
+	eprintln!("Usage: xswallow [options]");
+	eprintln!("  --adopt-existing  also swallow windows that are already open");
+	eprintln!("  --keep-hidden     don't reveal swallowed windows when quitting");
+	eprintln!("  --replace         take over from an already running instance");
+	eprintln!("  --reconnect       keep trying to reconnect if the X server goes away");
+	eprintln!("  --update-interval=MS");
+	eprintln!("                    handle window updates at most this often");
+}
+
+pub fn adopt_existing(count: usize) {
+	eprintln!("Adopting {count} existing windows");
+}
+
 pub fn window_vis(mode: bool, window: Window) {
 	eprintln!(
 		"- {} {}",
@@ -99,6 +161,10 @@ pub fn window_move(window: Window, pos: Geometry) {
 	eprintln!("- Moving {} to {}", MiniDebug(window), pos);
 }
 
+pub fn window_close(window: Window) {
+	eprintln!("- Closing {}", MiniDebug(window));
+}
+
 pub fn window_refocus(from: Window, to: Window) {
 	eprintln!(
 		"- Moving focus from {} to {}",
@@ -107,6 +173,41 @@ pub fn window_refocus(from: Window, to: Window) {
 	);
 }
 
+pub fn window_restack(window: Window, sibling: Option<Window>, mode: StackMode) {
+	let mode = match mode {
+		StackMode::Above => "above",
+		StackMode::Below => "below",
+		StackMode::TopIf => "on top if",
+		StackMode::BottomIf => "at the bottom if",
+		StackMode::Opposite => "opposite",
+	};
+	match sibling {
+		Some(sibling) => eprintln!(
+			"- Moving {} {} {}",
+			MiniDebug(window),
+			mode,
+			MiniDebug(sibling)
+		),
+		None => eprintln!("- Moving {} {} everything", MiniDebug(window), mode),
+	}
+}
+
+pub fn window_activate(window: Window) {
+	eprintln!("- Focusing {}", MiniDebug(window));
+}
+
+pub fn window_urgent(window: Window, urgent: bool) {
+	eprintln!(
+		"- {} {}",
+		if urgent {
+			"Marking urgent"
+		} else {
+			"Unmarking urgent"
+		},
+		MiniDebug(window)
+	);
+}
+
 pub fn flush_hard() {
 	eprintln!("- Hard event flush");
 }
@@ -115,15 +216,74 @@ pub fn find_next_parent(pid: u32, name: &[u8]) {
 	eprintln!("  → {} {:?}", pid, MiniDebug(name));
 }
 
+pub fn find_multiplexer_client(server: u32, client: u32) {
+	eprintln!("  ↪ {server} attached from {client}");
+}
+
+pub fn find_startup_id(id: &[u8], launcher: u32) {
+	eprintln!("  Startup id {} from {}", MiniDebug(id), launcher);
+}
+
 pub fn new_window(win: Window, pid: u32, name: &[u8]) {
 	eprintln!("New window: {} {} {}", MiniDebug(win), pid, MiniDebug(name));
 }
 
+pub fn remove_window(win: Window) {
+	eprintln!("Removed window: {}", MiniDebug(win));
+}
+
 pub fn find_parent_success(win: Window, pid: u32, name: &[u8]) {
 	eprintln!("  Parent: {} {} {}", MiniDebug(win), pid, MiniDebug(name));
 }
 
+pub fn parent_exit(win: Window, pid: u32, orphans: usize) {
+	eprintln!("Parent exited {} {}", MiniDebug(win), pid);
+	eprintln!("  Orphaned: {orphans}");
+}
+
+pub fn parent_urgent(win: Window, urgent: bool) {
+	eprintln!(
+		"Parent {} {}",
+		MiniDebug(win),
+		if urgent {
+			"wants attention"
+		} else {
+			"no longer wants attention"
+		}
+	);
+}
+
+pub fn bad_config(name: &str, value: &[u8]) {
+	eprintln!("Unknown value for {}: {}", name, MiniDebug(value));
+}
+
+pub fn restore_child(win: Window, parent: Window) {
+	eprintln!(
+		"Restored window: {} swallowing {}",
+		MiniDebug(win),
+		MiniDebug(parent)
+	);
+}
+
+pub fn dump(count: usize) {
+	eprintln!("Swallowed windows: {count}");
+}
+
+pub fn dump_child(win: Window, pid: u32, parent: Window, pos: Geometry) {
+	eprintln!(
+		"- {} {} in {} at {}",
+		MiniDebug(win),
+		pid,
+		MiniDebug(parent),
+		pos
+	);
+}
+
 pub fn close_window(win: Window, pid: u32, remaining: usize) {
 	eprintln!("Close window {} {}", MiniDebug(win), pid);
-	eprintln!("  Remaining: {}", remaining - 1);
+	eprintln!("  Remaining: {remaining}");
+}
+
+pub fn child_exit(parent: Window, pid: u32) {
+	eprintln!("Child exited {} in {}", pid, MiniDebug(parent));
 }
@@ -0,0 +1,207 @@
+//! abstraction over /proc (and some relevant things)
+//! all process-inspecting code goes here
+use std::collections::VecDeque;
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::os::fd::{FromRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::process::Command;
+use std::str::from_utf8;
+
+// [porting] use whatever sane-ish api exists, it's probably better than this
+/// programmer rolls “worst parser ever”, asked to leave /proc/{pid}/status
+pub fn get_pid_info(pid: u32) -> Option<(u32, Vec<u8>)> {
+	let mut file = BufReader::new(File::open(format!("/proc/{pid}/status")).ok()?);
+	let (mut ppid, mut name, mut line) = (None, None, Vec::new());
+	while ppid.is_none() || name.is_none() {
+		line.clear();
+		file.read_until(b'\n', &mut line).ok()?;
+		line.pop();
+		if let Some(ppid_text) = line.strip_prefix(b"PPid:\t") {
+			ppid = Some(from_utf8(ppid_text).ok()?.parse().ok()?);
+		} else if let Some(name_text) = line.strip_prefix(b"Name:\t") {
+			#[cfg_attr(rust_analyzer, expect(unused_mut, reason = "rust-analyzer#18209"))]
+			let mut normal = true;
+			let iter = name_text.iter().filter_map(|&(mut ch)| {
+				(normal, ch) = match ch {
+					b'\\' if normal => (false, ch),
+					b'n' if !normal => (true, b'\n'),
+					_ => (true, ch),
+				};
+				normal.then_some(ch)
+			});
+			name = Some(iter.collect());
+		}
+	}
+	Some((ppid.unwrap(), name.unwrap()))
+}
+
+/// a pid along with when it started, since pids wrap around and get reused
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProcessId {
+	pub pid: u32,
+	/// in clock ticks since boot
+	start_time: u64,
+}
+
+impl ProcessId {
+	pub fn new(pid: u32) -> Option<Self> {
+		Some(Self {
+			pid,
+			start_time: get_pid_start_time(pid)?,
+		})
+	}
+	/// check that this is still the same process, not something that took its
+	/// pid
+	pub fn is_alive(self) -> bool {
+		get_pid_start_time(self.pid) == Some(self.start_time)
+	}
+	/// file descriptor that becomes readable when the process exits
+	// [porting] needs linux 5.3, other platforms might have kqueue or similar
+	pub fn open_pidfd(self) -> Option<OwnedFd> {
+		// SAFETY: pidfd_open doesn't touch memory
+		let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, self.pid, 0) };
+		let fd = i32::try_from(fd).ok().filter(|&fd| fd >= 0)?;
+		// SAFETY: the syscall just returned a new fd that nothing else owns
+		let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+		// the pid could have been reused between getting the id and opening it
+		self.is_alive().then_some(fd)
+	}
+}
+
+/// field 22 of /proc/{pid}/stat
+fn get_pid_start_time(pid: u32) -> Option<u64> {
+	let stat = fs::read(format!("/proc/{pid}/stat")).ok()?;
+	// the name can contain anything (including spaces and parentheses),
+	// but it's always the last thing in parentheses
+	let name_end = stat.iter().rposition(|&c| c == b')')?;
+	let fields = from_utf8(stat.get(name_end + 2..)?).ok()?;
+	fields.split(' ').nth(19)?.parse().ok()
+}
+
+/// read one variable out of /proc/{pid}/environ
+pub fn get_pid_env(pid: u32, name: &[u8]) -> Option<Vec<u8>> {
+	let environ = fs::read(format!("/proc/{pid}/environ")).ok()?;
+	environ.split(|&c| c == 0).find_map(|entry| {
+		let value = entry.strip_prefix(name)?.strip_prefix(b"=")?;
+		Some(value.to_vec())
+	})
+}
+
+/// find a process under one of `roots` by an environment variable,
+/// e.g. the launcher that was given a specific `DESKTOP_STARTUP_ID`,
+/// closest to the roots first
+pub fn find_descendant_with_env(
+	roots: impl IntoIterator<Item = u32>,
+	name: &[u8],
+	value: &[u8],
+) -> Option<u32> {
+	let mut queue = roots.into_iter().collect::<VecDeque<_>>();
+	while let Some(pid) = queue.pop_front() {
+		if get_pid_env(pid, name).is_some_and(|env| env == value) {
+			return Some(pid);
+		}
+		queue.extend(get_pid_children(pid));
+	}
+	None
+}
+
+/// processes started by any of this process's threads
+fn get_pid_children(pid: u32) -> Vec<u32> {
+	fs::read_dir(format!("/proc/{pid}/task"))
+		.into_iter()
+		.flatten()
+		.filter_map(|entry| fs::read(entry.ok()?.path().join("children")).ok())
+		.flat_map(|children| {
+			children
+				.split(|&c| c == b' ')
+				.filter_map(|child| from_utf8(child).ok()?.parse().ok())
+				.collect::<Vec<_>>()
+		})
+		.collect()
+}
+
+/// terminal devices a process has open, e.g. `/dev/pts/3`
+fn get_pid_ttys(pid: u32) -> impl Iterator<Item = Vec<u8>> {
+	fs::read_dir(format!("/proc/{pid}/fd"))
+		.into_iter()
+		.flatten()
+		.filter_map(|entry| fs::read_link(entry.ok()?.path()).ok())
+		.map(|path| path.as_os_str().as_bytes().to_vec())
+		.filter(|path| path.starts_with(b"/dev/pts/") || path.starts_with(b"/dev/tty"))
+}
+
+/// every pid currently running
+fn all_pids() -> impl Iterator<Item = u32> {
+	fs::read_dir("/proc")
+		.into_iter()
+		.flatten()
+		.filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+}
+
+/// terminal multiplexers that sit between a terminal and its programs
+#[derive(Clone, Copy)]
+pub enum Multiplexer {
+	Tmux,
+	Screen,
+}
+
+impl Multiplexer {
+	/// recognise a multiplexer server by its process name
+	pub fn from_name(name: &[u8]) -> Option<Self> {
+		match name {
+			b"tmux: server" => Some(Self::Tmux),
+			b"screen" => Some(Self::Screen),
+			_ => None,
+		}
+	}
+	/// find the client process that displays the session `pane_pid` is in,
+	/// that client is running inside the terminal that should get swallowed
+	pub fn find_client(self, server_pid: u32, pane_pid: u32) -> Option<u32> {
+		match self {
+			Self::Tmux => Self::find_tmux_client(pane_pid),
+			Self::Screen => Self::find_screen_client(server_pid),
+		}
+	}
+	/// tmux knows which clients are attached to which session,
+	/// so ask it (using the socket the pane was started with)
+	fn find_tmux_client(pane_pid: u32) -> Option<u32> {
+		// `TMUX` is formatted as "{socket},{server pid},{session index}"
+		let env = get_pid_env(pane_pid, b"TMUX")?;
+		let mut parts = env.rsplitn(3, |&c| c == b',');
+		let session = format!("${}", from_utf8(parts.next()?).ok()?);
+		let socket = parts.nth(1)?;
+		let output = Command::new("tmux")
+			.arg("-S")
+			.arg(OsStr::from_bytes(socket))
+			.args([
+				"list-clients",
+				"-F",
+				"#{client_activity} #{client_pid} #{session_id}",
+			])
+			.output()
+			.ok()?;
+		// the most recently used client is probably the one the user is looking at
+		output
+			.stdout
+			.split(|&c| c == b'\n')
+			.filter_map(|line| {
+				let mut fields = from_utf8(line).ok()?.split(' ');
+				let activity = fields.next()?.parse::<u64>().ok()?;
+				let pid = fields.next()?.parse::<u32>().ok()?;
+				(fields.next()? == session).then_some((activity, pid))
+			})
+			.max()
+			.map(|(_, pid)| pid)
+	}
+	/// screen has one server per session and writes directly to the tty of
+	/// each attached client, so find another screen process using that tty
+	fn find_screen_client(server_pid: u32) -> Option<u32> {
+		let ttys = get_pid_ttys(server_pid).collect::<Vec<_>>();
+		all_pids()
+			.filter(|&pid| pid != server_pid)
+			.filter(|&pid| get_pid_info(pid).is_some_and(|(_, name)| name == b"screen"))
+			.find(|&pid| get_pid_ttys(pid).any(|tty| ttys.contains(&tty)))
+	}
+}
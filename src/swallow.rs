@@ -1,87 +1,186 @@
 //! core application logic
+use std::cell::Cell;
 use std::convert::Infallible;
-use std::env::var_os;
-use std::os::unix::ffi::OsStringExt;
 use std::rc::{Rc, Weak};
 
 use foldhash::fast::RandomState;
 use foldhash::{HashMap, HashSet};
 use weak_table::WeakValueHashMap;
 use weak_table::weak_value_hash_map::Entry as WvhmEntry;
-use xcb::x::Window;
+use xcb::x::{StackMode, Window};
 
-use crate::context::{Context, Geometry, get_pid_info};
+use crate::config::{
+	FocusPolicy, OrphanPolicy, Rule, Rules, UrgencyPolicy, VomitCondition, WindowChoice, env_bytes,
+	env_flag, env_iter,
+};
+use crate::context::{Configure, Context, Geometry, HideStrategy, Inherit, Layout, WindowState};
+use crate::diff::list_diff;
 use crate::output;
+use crate::process::{Multiplexer, ProcessId, find_descendant_with_env, get_pid_info};
 
 struct Parent {
+	pid: ProcessId,
 	window: Window,
 	/// position not updated with children, for use when quitting
 	position: Geometry,
+	/// from the rule of the child that swallowed it first
+	hide: HideStrategy,
+	urgency: UrgencyPolicy,
+	layout: Layout,
+	vomit_on: VomitCondition,
+	/// the child that swallowed it first
+	primary: Window,
+	primary_pid: ProcessId,
+	/// as of the last time it changed
+	urgent: Cell<bool>,
 }
 
 struct Child {
-	pid: u32,
+	pid: ProcessId,
 	parent: Rc<Parent>,
 	position: Geometry,
+	rule: Rule,
+	/// the window right above it as of the last stacking change, since the
+	/// window manager drops it from the list before it's closed
+	above: Option<Window>,
 }
 
-/// optimized to work for lists where the prefix is the same
-/// as is the case with `_NET_WM_CLIENT_LIST`
-fn list_diff<T: Eq + Copy, F: FnMut(T) -> R, R>(target: &mut Vec<T>, source: &[T], mut call: F) {
-	for (i, val) in source.iter().enumerate() {
-		let j = target
-			.iter()
-			.enumerate()
-			.skip(i)
-			.find_map(|(j, test)| (test == val).then_some(j))
-			.unwrap_or_else(|| {
-				call(*val);
-				target.push(*val);
-				target.len() - 1
-			});
-		target.swap(i, j);
+/// which client-list windows belong to which process,
+/// kept up to date from the list diffs so finding a parent doesn't need to ask
+/// every window for its pid again
+#[derive(Default)]
+struct PidIndex {
+	pids: HashMap<Window, u32>,
+	windows: HashMap<u32, Vec<Window>>,
+}
+
+impl PidIndex {
+	fn insert(&mut self, cx: &Context, windows: &[Window]) {
+		for (window, pid) in cx.window_pids(windows) {
+			self.pids.insert(window, pid);
+			self.windows.entry(pid).or_default().push(window);
+		}
+	}
+	fn remove(&mut self, window: Window) {
+		let Some(pid) = self.pids.remove(&window) else {
+			return;
+		};
+		if let Some(windows) = self.windows.get_mut(&pid) {
+			windows.retain(|&other| other != window);
+			if windows.is_empty() {
+				self.windows.remove(&pid);
+			}
+		}
+	}
+	fn pid(&self, window: Window) -> Option<u32> {
+		self.pids.get(&window).copied()
+	}
+	/// every process with a window
+	fn pids(&self) -> impl Iterator<Item = u32> + '_ {
+		self.windows.keys().copied()
+	}
+	/// oldest first
+	fn windows(&self, pid: u32) -> &[Window] {
+		self.windows.get(&pid).map_or(&[], Vec::as_slice)
 	}
-	target.truncate(source.len());
 }
 
-/// main pid-walking algorithm
-/// can't be on `State` because it's borrowed by `list_diff`
+/// main pid-walking algorithm, `child_pid` is the process `parent_pid` is the
+/// parent of, or 0 if that's not known
 fn find_parent(
+	mut child_pid: u32,
 	mut parent_pid: u32,
 	immune_names: &HashSet<Rc<[u8]>>,
-	terminals_names: &HashSet<Rc<[u8]>>,
+	parent_names: &HashSet<Rc<[u8]>>,
 ) -> Option<(u32, Vec<u8>)> {
+	// the process below the current one is needed to find a multiplexer's
+	// session, even if that's the window's own process
+	// screen's client and server have the same name, so jumping from the
+	// client would go right back to the server
+	let mut jumped = false;
+	let mut visited = HashSet::default();
 	while parent_pid > 0 {
+		if !visited.insert(parent_pid) {
+			return None;
+		}
 		let (next_ppid, parent_name) = get_pid_info(parent_pid)?;
 		output::find_next_parent(parent_pid, &parent_name);
-		if terminals_names.contains(parent_name.as_slice()) {
+		if parent_names.contains(parent_name.as_slice()) {
 			return Some((parent_pid, parent_name));
 		} else if immune_names.contains(parent_name.as_slice()) {
 			return None;
 		}
-		parent_pid = next_ppid;
+		// a multiplexer server isn't below the terminal,
+		// so jump over to the client attached to it
+		if let Some(client_pid) = Multiplexer::from_name(&parent_name)
+			.filter(|_| !jumped)
+			.and_then(|multiplexer| multiplexer.find_client(parent_pid, child_pid))
+		{
+			output::find_multiplexer_client(parent_pid, client_pid);
+			(child_pid, parent_pid, jumped) = (0, client_pid, true);
+			continue;
+		}
+		(child_pid, parent_pid, jumped) = (parent_pid, next_ppid, false);
 	}
 	None
 }
 
-// TODO: replace with a real configuration file
-fn env_bytes(name: &str) -> Vec<u8> {
-	// since reading the /proc/{pid}/status returns arbitrary bytes,
-	// i need to support more than utf-8, so i use the unix strings
-	// [porting] figure out how your platform does process names
-	var_os(name).unwrap_or_default().into_vec()
+fn window_above(stacking: &[Window], window: Window) -> Option<Window> {
+	let index = stacking.iter().position(|&other| other == window)?;
+	stacking.get(index + 1).copied()
 }
-fn env_iter(text: &[u8]) -> impl Iterator<Item = Rc<[u8]>> + '_ {
-	let iter = text.split(|&c| c == b':').map(|item| item.to_vec().into());
-	(!text.is_empty()).then_some(iter).into_iter().flatten()
+
+/// reveal the parent in the child's place
+fn vomit(cx: &Context, win: Window, child: &Child) {
+	let Child {
+		parent,
+		position,
+		rule,
+		above,
+		..
+	} = child;
+	let mut inherit = rule.vomit;
+	if parent.urgent.get() {
+		// the child only has it because of the parent
+		inherit.state = inherit.state.without(WindowState::DEMANDS_ATTENTION);
+	}
+	let position = parent.position.inherit(*position, inherit);
+	cx.unwatch_process(parent.pid);
+	cx.unsubscribe(parent.window);
+	cx.forget_parent(parent.window);
+	// specific order to prevent “not working”
+	cx.set_window_geometry(parent.window, position);
+	cx.show_window(parent.window, parent.hide);
+	match *above {
+		Some(above) => cx.restack_window(parent.window, Some(above), StackMode::Below),
+		None => cx.restack_window(parent.window, None, StackMode::Above),
+	}
+	match rule.focus {
+		FocusPolicy::Always => cx.activate_window(parent.window),
+		FocusPolicy::IfFocused => _ = cx.set_window_active_if(win, parent.window),
+		FocusPolicy::Never => {}
+	}
+	cx.set_window_geometry(parent.window, position);
+	// not sure if i need this
+	cx.flush();
 }
 
 pub struct Swallow {
 	immune_names: HashSet<Rc<[u8]>>,
-	terminal_names: HashSet<Rc<[u8]>>,
+	/// terminals and anything else that can swallow
+	parent_names: HashSet<Rc<[u8]>>,
+	orphans: OrphanPolicy,
+	rules: Rules,
 	all_windows: Vec<Window>,
-	parent_table: WeakValueHashMap<u32, Weak<Parent>, RandomState>,
+	/// `_NET_CLIENT_LIST_STACKING`, as of the last event that was handled
+	stacking: Vec<Window>,
+	pid_index: PidIndex,
+	/// by window, since a process can have several that are hidden separately
+	parent_table: WeakValueHashMap<Window, Weak<Parent>, RandomState>,
 	child_table: HashMap<Window, Child>,
+	/// closed children of `VomitCondition::Exit` parents, keeping them hidden
+	/// until the process exits
+	lingering: Vec<(Window, Child)>,
 }
 
 impl Swallow {
@@ -91,88 +190,525 @@ impl Swallow {
 		terminal_names.insert(env_bytes("TERMINAL").into());
 		terminal_names.extend(env_iter(&env_bytes("XSWALLOW_TERMINALS")));
 		immune_names.extend(env_iter(&env_bytes("XSWALLOW_IMMUNE")));
-		// wouldn't really make sense to swallow a terminal into a terminal
-		immune_names.extend(terminal_names.iter().cloned());
-		output::setup_state(&immune_names, &terminal_names);
-		Ok(Self {
+		// a terminal swallowing a terminal only makes sense if that one can
+		// swallow something else in turn
+		if !env_flag("XSWALLOW_NESTED") {
+			immune_names.extend(terminal_names.iter().cloned());
+		}
+		// e.g. file managers, not immune since they're not terminals
+		let mut parent_names = terminal_names;
+		parent_names.extend(env_iter(&env_bytes("XSWALLOW_PARENTS")));
+		output::setup_state(&immune_names, &parent_names);
+		let mut this = Self {
 			immune_names,
-			terminal_names,
+			parent_names,
+			orphans: OrphanPolicy::from_env("XSWALLOW_ORPHANS"),
+			rules: Rules::from_env("XSWALLOW_RULES"),
 			all_windows: cx.get_window_list()?.value().to_vec(),
+			stacking: cx.get_stacking(),
+			pid_index: PidIndex::default(),
 			parent_table: WeakValueHashMap::default(),
 			child_table: HashMap::default(),
-		})
+			lingering: Vec::new(),
+		};
+		this.pid_index.insert(cx, &this.all_windows);
+		for &window in &this.all_windows {
+			cx.request_frame_extents(window);
+		}
+		this.restore(cx);
+		Ok(this)
+	}
+	/// pick up the pairings a previous instance left on the windows
+	fn restore(&mut self, cx: &Context) {
+		let parents = cx.load_parent_list();
+		// hidden parents might have been swallowed themselves
+		let windows = self.all_windows.iter().chain(&parents).copied();
+		for child_window in windows.collect::<Vec<_>>() {
+			let Some(parent_window) = cx.load_child(child_window) else {
+				continue;
+			};
+			if self.child_table.contains_key(&child_window) {
+				continue;
+			}
+			if !parents.contains(&parent_window)
+				|| self
+					.restore_child(cx, child_window, parent_window)
+					.is_none()
+			{
+				cx.forget_child(child_window);
+			}
+		}
+		// children that closed while nobody was watching
+		for parent_window in parents {
+			if self.parent_table.contains_key(&parent_window) {
+				continue;
+			}
+			if let Some((position, hide)) = cx.load_parent(parent_window) {
+				cx.set_window_geometry(parent_window, position);
+				cx.show_window(parent_window, hide);
+				cx.set_window_geometry(parent_window, position);
+			}
+			cx.forget_parent(parent_window);
+		}
+		self.save_parent_list(cx);
+		cx.flush();
+	}
+	fn restore_child(
+		&mut self,
+		cx: &Context,
+		child_window: Window,
+		parent_window: Window,
+	) -> Option<()> {
+		let parent_pid = ProcessId::new(cx.window_pid(parent_window)?)?;
+		let child_pid = ProcessId::new(cx.window_pid(child_window)?)?;
+		let (_, child_name) = get_pid_info(child_pid.pid)?;
+		let rule = self.rules.get(&child_name);
+		// before registering anything, so nothing is left watched on failure
+		let position = cx.get_window_geometry(child_window)?;
+		let parent = match self.parent_table.entry(parent_window) {
+			WvhmEntry::Occupied(occupied) => occupied.get_strong(),
+			WvhmEntry::Vacant(vacant) => {
+				let (position, hide) = cx.load_parent(parent_window)?;
+				let parent = vacant.insert(Rc::new(Parent {
+					pid: parent_pid,
+					window: parent_window,
+					position,
+					hide,
+					urgency: rule.urgency,
+					layout: rule.layout,
+					vomit_on: rule.vomit_on,
+					primary: child_window,
+					primary_pid: child_pid,
+					urgent: Cell::new(false),
+				}));
+				cx.watch_process(parent_pid);
+				if let VomitCondition::Exit = rule.vomit_on {
+					cx.watch_process(child_pid);
+				}
+				// to see it wanting attention
+				cx.subscribe(parent_window);
+				parent
+			}
+		};
+		output::restore_child(child_window, parent_window);
+		cx.subscribe(child_window);
+		self.child_table.insert(child_window, Child {
+			pid: child_pid,
+			parent,
+			position,
+			rule,
+			above: window_above(&self.stacking, child_window),
+		});
+		Some(())
+	}
+	/// keep the root's list of hidden parents in sync with `parent_table`
+	fn save_parent_list(&self, cx: &Context) {
+		let parents = self
+			.parent_table
+			.values()
+			.map(|parent| parent.window)
+			.collect::<Vec<_>>();
+		cx.save_parent_list(&parents);
 	}
 	pub fn window_list(&mut self, cx: &Context) -> Option<Infallible> {
 		let new_windows = cx.get_window_list().ok()?;
 		let new_windows = new_windows.value::<Window>();
-		list_diff(&mut self.all_windows, new_windows, |child_window| {
-			let child_pid = cx.window_pid(child_window)?;
-			let (parent_pid, child_name) = get_pid_info(child_pid)?;
-			output::new_window(child_window, child_pid, &child_name);
-			if !self.immune_names.contains(child_name.as_slice()) {
-				return None;
-			}
-			let (parent_pid, parent_name) =
-				find_parent(parent_pid, &self.immune_names, &self.terminal_names)?;
-			let parent_window = cx.find_window_with_pid(parent_pid, new_windows)?;
-			output::find_parent_success(parent_window, parent_pid, &parent_name);
-			let (parent, position);
-			match self.parent_table.entry(parent_pid) {
-				WvhmEntry::Occupied(occupied) => {
-					position = cx.get_window_geometry(child_window)?;
-					parent = occupied.get_strong();
-				}
-				WvhmEntry::Vacant(vacant) => {
-					position = cx.get_window_geometry(parent_window)?;
-					parent = vacant.insert(Rc::new(Parent {
-						window: parent_window,
-						position,
-					}));
-					cx.hide_window(parent_window);
-					cx.set_window_geometry(child_window, position);
-				}
+		let (added, removed) = list_diff(&mut self.all_windows, new_windows);
+		for window in removed {
+			output::remove_window(window);
+			self.pid_index.remove(window);
+		}
+		// index everything first, the parent might have appeared in the same batch
+		self.pid_index.insert(cx, &added);
+		for window in added {
+			cx.request_frame_extents(window);
+			self.new_window(cx, window);
+		}
+		None
+	}
+	pub fn stacking(&mut self, cx: &Context) -> Option<Infallible> {
+		self.stacking = cx.get_stacking();
+		// closed windows are gone from the list, so keep whatever they had last
+		for (&win, child) in &mut self.child_table {
+			if self.stacking.contains(&win) {
+				child.above = window_above(&self.stacking, win);
 			}
-			cx.subscribe(child_window);
-			cx.flush();
-			self.parent_table.insert(parent_pid, parent.clone());
-			self.child_table.insert(child_window, Child {
-				pid: child_pid,
-				parent,
-				position,
+		}
+		None
+	}
+	/// pick which of the parent process's windows gets hidden, `choice` only
+	/// matters if none of them are yet
+	fn parent_window(
+		&self,
+		child_pid: ProcessId,
+		parent_pid: ProcessId,
+		choice: WindowChoice,
+	) -> Option<Window> {
+		// more windows from a program go with the one it already swallowed
+		if let Some(child) = self
+			.child_table
+			.values()
+			.find(|child| child.pid == child_pid && child.parent.pid == parent_pid)
+		{
+			return Some(child.parent.window);
+		}
+		// it was probably launched from the window that's hidden already, since
+		// that's the one being used, so it joins in (and gets tiled with it)
+		if let Some(parent) = self
+			.parent_table
+			.values()
+			.find(|parent| parent.pid == parent_pid)
+		{
+			return Some(parent.window);
+		}
+		let candidates = self.pid_index.windows(parent_pid.pid);
+		match choice {
+			WindowChoice::Oldest => candidates.first().copied(),
+			WindowChoice::Newest => candidates.last().copied(),
+			WindowChoice::Topmost => self
+				.stacking
+				.iter()
+				.rev()
+				.find(|window| candidates.contains(window))
+				.or(candidates.last())
+				.copied(),
+		}
+	}
+	/// treat every window that was open before starting as if it just opened,
+	/// so the state matches what it would be if xswallow had been running
+	pub fn adopt_existing(&mut self, cx: &Context) {
+		let windows = self.all_windows.clone();
+		output::adopt_existing(windows.len());
+		for &window in &windows {
+			if !self.child_table.contains_key(&window) {
+				self.new_window(cx, window);
+			}
+		}
+	}
+	/// main swallowing decision
+	fn new_window(&mut self, cx: &Context, child_window: Window) -> Option<()> {
+		let child_pid = self.pid_index.pid(child_window)?;
+		let (parent_pid, child_name) = get_pid_info(child_pid)?;
+		let child_pid = ProcessId::new(child_pid)?;
+		output::new_window(child_window, child_pid.pid, &child_name);
+		let rule = self.rules.get(&child_name);
+		// window ids get reused too, so whatever was here before is gone
+		if self
+			.child_table
+			.get(&child_window)
+			.is_some_and(|stale| stale.pid != child_pid || !stale.pid.is_alive())
+		{
+			self.close(cx, child_window);
+		}
+		if self.immune_names.contains(child_name.as_slice()) {
+			return None;
+		}
+		let (parent_pid, parent_name) = find_parent(
+			child_pid.pid,
+			parent_pid,
+			&self.immune_names,
+			&self.parent_names,
+		)
+		.or_else(|| {
+			// window might belong to an already-running instance, so find
+			// whoever launched it and check that process's terminal instead,
+			// which means it's somewhere under a terminal with a window
+			let startup_id = cx.window_startup_id(child_window)?;
+			// the window's own process can be parent-named too, and would find
+			// itself
+			let parents = self.pid_index.pids().filter(|&pid| {
+				pid != child_pid.pid
+					&& get_pid_info(pid)
+						.is_some_and(|(_, name)| self.parent_names.contains(name.as_slice()))
 			});
-			Some(())
+			let launcher_pid =
+				find_descendant_with_env(parents, b"DESKTOP_STARTUP_ID", &startup_id)
+					.filter(|&pid| pid != child_pid.pid)?;
+			output::find_startup_id(&startup_id, launcher_pid);
+			find_parent(0, launcher_pid, &self.immune_names, &self.parent_names)
+		})?;
+		let parent_pid = ProcessId::new(parent_pid)?;
+		let parent_window = self
+			.parent_window(child_pid, parent_pid, self.rules.get(&parent_name).windows)
+			.or_else(|| cx.find_window_in_tree(parent_pid.pid))?;
+		// a window can't swallow itself
+		if parent_window == child_window {
+			return None;
+		}
+		output::find_parent_success(parent_window, parent_pid.pid, &parent_name);
+		let (parent, position);
+		match self.parent_table.entry(parent_window) {
+			WvhmEntry::Occupied(occupied) => {
+				position = cx.get_window_geometry(child_window)?;
+				parent = occupied.get_strong();
+			}
+			WvhmEntry::Vacant(vacant) => {
+				let parent_position = cx.get_window_geometry(parent_window)?;
+				position = cx
+					.get_window_geometry(child_window)?
+					.inherit(parent_position, rule.swallow);
+				parent = vacant.insert(Rc::new(Parent {
+					pid: parent_pid,
+					window: parent_window,
+					position: parent_position,
+					hide: rule.hide,
+					urgency: rule.urgency,
+					layout: rule.layout,
+					vomit_on: rule.vomit_on,
+					primary: child_window,
+					primary_pid: child_pid,
+					urgent: Cell::new(false),
+				}));
+				// take the parent's place in the stack
+				cx.restack_window(child_window, Some(parent_window), StackMode::Above);
+				cx.hide_window(parent_window, rule.hide);
+				cx.save_parent(parent_window, parent_position, rule.hide);
+				cx.watch_process(parent_pid);
+				if let VomitCondition::Exit = rule.vomit_on {
+					cx.watch_process(child_pid);
+				}
+				cx.subscribe(parent_window);
+				cx.set_window_geometry(child_window, position);
+			}
+		}
+		cx.subscribe(child_window);
+		cx.save_child(child_window, parent.window);
+		self.parent_table.insert(parent.window, parent.clone());
+		self.child_table.insert(child_window, Child {
+			pid: child_pid,
+			parent: parent.clone(),
+			position,
+			rule,
+			above: window_above(&self.stacking, child_window),
 		});
-		None
+		// the parent might already want attention from before this child
+		if parent.urgent.get() && matches!(parent.urgency, UrgencyPolicy::Mirror) {
+			cx.set_demands_attention(child_window, true);
+		}
+		self.tile(cx, &parent);
+		self.save_parent_list(cx);
+		cx.flush();
+		Some(())
 	}
 	pub fn update(&mut self, cx: &Context, win: Window) -> Option<Infallible> {
+		if self.is_hidden_parent(win) {
+			// `_NET_WM_STATE_DEMANDS_ATTENTION` might have changed
+			return self.hints(cx, win);
+		}
 		self.child_table.get_mut(&win)?.position = cx.get_window_geometry(win)?;
 		None
 	}
+	/// when nesting, a child can be hidden too, and shouldn't get the hidden
+	/// position recorded
+	fn is_hidden_parent(&self, win: Window) -> bool {
+		self.parent_table.contains_key(&win)
+	}
+	/// pass on a hidden parent's urgency according to its policy
+	pub fn hints(&mut self, cx: &Context, win: Window) -> Option<Infallible> {
+		let parent = self.parent_table.get(&win)?;
+		let urgent = cx.is_urgent(win);
+		if parent.urgent.replace(urgent) == urgent {
+			return None;
+		}
+		output::parent_urgent(win, urgent);
+		match parent.urgency {
+			UrgencyPolicy::Mirror => {
+				for &child in self.children_of(&parent) {
+					cx.set_demands_attention(child, urgent);
+				}
+			}
+			UrgencyPolicy::Reveal if urgent => self.unswallow(cx, &parent),
+			UrgencyPolicy::Ignore | UrgencyPolicy::Reveal => {}
+		}
+		cx.flush();
+		None
+	}
+	fn children_of<'a>(&'a self, parent: &'a Rc<Parent>) -> impl Iterator<Item = &'a Window> {
+		self.child_table
+			.iter()
+			.filter_map(|(win, child)| Rc::ptr_eq(&child.parent, parent).then_some(win))
+	}
+	/// stop tracking a parent that's about to be revealed, or is gone
+	fn drop_parent(&mut self, cx: &Context, parent: &Rc<Parent>) {
+		self.parent_table.remove(&parent.window);
+		self.lingering
+			.retain(|(_, child)| !Rc::ptr_eq(&child.parent, parent));
+		if let VomitCondition::Exit = parent.vomit_on {
+			cx.unwatch_process(parent.primary_pid);
+		}
+	}
+	/// reveal a parent while its children stay open, and stop tracking them
+	fn unswallow(&mut self, cx: &Context, parent: &Rc<Parent>) {
+		let children = self.children_of(parent).copied().collect::<Vec<_>>();
+		for child in children {
+			self.child_table.remove(&child);
+			cx.forget_child(child);
+		}
+		self.drop_parent(cx, parent);
+		cx.unwatch_process(parent.pid);
+		cx.unsubscribe(parent.window);
+		cx.forget_parent(parent.window);
+		cx.set_window_geometry(parent.window, parent.position);
+		cx.show_window(parent.window, parent.hide);
+		cx.set_window_geometry(parent.window, parent.position);
+		self.save_parent_list(cx);
+	}
+	pub fn configure(
+		&mut self,
+		cx: &Context,
+		win: Window,
+		configure: Configure,
+	) -> Option<Infallible> {
+		if self.is_hidden_parent(win) {
+			return None;
+		}
+		let position = &mut self.child_table.get_mut(&win)?.position;
+		if !position.configure(configure) {
+			return self.update(cx, win);
+		}
+		// might have moved to another monitor
+		*position = cx.locate(*position);
+		None
+	}
 	pub fn close(&mut self, cx: &Context, win: Window) -> Option<Infallible> {
-		let Child {
-			pid,
-			parent,
-			position,
-		} = self.child_table.remove(&win)?;
-		output::close_window(win, pid, Rc::strong_count(&parent));
-		// no more child windows open
-		if Rc::strong_count(&parent) == 1 {
-			// specific order to prevent “not working”
-			cx.set_window_geometry(parent.window, position);
-			cx.show_window(parent.window);
-			cx.set_window_active_if(win, parent.window);
-			cx.set_window_geometry(parent.window, position);
-			// not sure if i need this
-			cx.flush();
+		let child = self.child_table.remove(&win)?;
+		let parent = child.parent.clone();
+		let remaining = self.children_of(&parent).count();
+		output::close_window(win, child.pid.pid, remaining);
+		match parent.vomit_on {
+			// the process might still be doing something
+			VomitCondition::Exit if remaining == 0 && parent.primary_pid.is_alive() => {
+				self.lingering.push((win, child));
+			}
+			// or it's already gone without the exit being noticed
+			VomitCondition::Last | VomitCondition::Exit if remaining == 0 => {
+				self.reveal(cx, win, &child);
+			}
+			VomitCondition::Primary if win == parent.primary => {
+				self.reveal(cx, win, &child);
+			}
+			// the others get the space back
+			_ => self.tile(cx, &parent),
 		}
+		self.save_parent_list(cx);
 		None
 	}
-	pub fn quit(&mut self, cx: &Context) {
-		// show all the windows that were hidden
-		for parent in self.parent_table.values() {
-			cx.set_window_geometry(parent.window, parent.position);
-			cx.show_window(parent.window);
-			cx.set_window_geometry(parent.window, parent.position);
+	/// reveal the parent in place of `child`, and stop tracking whatever else
+	/// it swallowed, which stays open
+	fn reveal(&mut self, cx: &Context, win: Window, child: &Child) {
+		let parent = &child.parent;
+		let others = self.children_of(parent).copied().collect::<Vec<_>>();
+		for other in others {
+			self.child_table.remove(&other);
+			if parent.urgent.get() {
+				cx.set_demands_attention(other, false);
+			}
+			cx.forget_child(other);
+		}
+		self.drop_parent(cx, parent);
+		vomit(cx, win, child);
+	}
+	/// lay out all of a parent's children inside where it was, oldest first
+	fn tile(&mut self, cx: &Context, parent: &Rc<Parent>) {
+		let mut children = self.children_of(parent).copied().collect::<Vec<_>>();
+		children.sort_by_key(|child| {
+			self.all_windows
+				.iter()
+				.position(|window| window == child)
+				.unwrap_or(usize::MAX)
+		});
+		for (index, &win) in children.iter().enumerate() {
+			let Some(tile) = parent.position.tile(parent.layout, index, children.len()) else {
+				return;
+			};
+			let Some(child) = self.child_table.get_mut(&win) else {
+				continue;
+			};
+			// the child's frame might not be the same size as the parent's
+			child.position = child.position.inherit(tile, Inherit::TILE).fit_frame(tile);
+			cx.set_window_geometry(win, child.position);
+		}
+	}
+	/// a hidden parent is gone, so there's nothing to vomit into anymore,
+	/// or a child is, and its parent can come back
+	pub fn process_exit(&mut self, cx: &Context, pid: ProcessId) -> Option<Infallible> {
+		self.child_exit(cx, pid);
+		let parents = self
+			.parent_table
+			.values()
+			.filter(|parent| parent.pid == pid)
+			.collect::<Vec<_>>();
+		for parent in parents {
+			self.drop_parent(cx, &parent);
+			let orphans = self.children_of(&parent).copied().collect::<Vec<_>>();
+			output::parent_exit(parent.window, pid.pid, orphans.len());
+			for win in orphans {
+				self.child_table.remove(&win);
+				if parent.urgent.get() {
+					cx.set_demands_attention(win, false);
+				}
+				match self.orphans {
+					OrphanPolicy::Leave => cx.forget_child(win),
+					OrphanPolicy::Close => cx.close_window(win),
+				}
+			}
+		}
+		self.save_parent_list(cx);
+		cx.flush();
+		None
+	}
+	/// reveal the parents waiting for this process with `VomitCondition::Exit`
+	fn child_exit(&mut self, cx: &Context, pid: ProcessId) {
+		let waiting = |child: &Child| {
+			matches!(child.parent.vomit_on, VomitCondition::Exit) && child.parent.primary_pid == pid
+		};
+		// the windows might not have been closed yet, or be someone else's
+		while let Some(win) = self
+			.child_table
+			.iter()
+			.find_map(|(&win, child)| waiting(child).then_some(win))
+		{
+			let Some(child) = self.child_table.remove(&win) else {
+				break;
+			};
+			output::child_exit(child.parent.window, pid.pid);
+			self.reveal(cx, win, &child);
+		}
+		let closed = self
+			.lingering
+			.extract_if(.., |(_, child)| waiting(child))
+			.collect::<Vec<_>>();
+		for (win, child) in closed {
+			output::child_exit(child.parent.window, pid.pid);
+			self.reveal(cx, win, &child);
+		}
+	}
+	/// print every swallowed window, for debugging
+	pub fn dump(&self) -> Option<Infallible> {
+		output::dump(self.child_table.len());
+		for (&win, child) in &self.child_table {
+			output::dump_child(win, child.pid.pid, child.parent.window, child.position);
+		}
+		None
+	}
+	/// `reveal` is false when the next instance should adopt the hidden parents
+	pub fn quit(&mut self, cx: &Context, reveal: bool) {
+		if reveal {
+			// show all the windows that were hidden
+			for parent in self.parent_table.values() {
+				cx.set_window_geometry(parent.window, parent.position);
+				cx.show_window(parent.window, parent.hide);
+				cx.set_window_geometry(parent.window, parent.position);
+				cx.forget_parent(parent.window);
+			}
+			for (&win, child) in &self.child_table {
+				// only there because of the parent
+				if child.parent.urgent.get() {
+					cx.set_demands_attention(win, false);
+				}
+				cx.forget_child(win);
+			}
+			cx.save_parent_list(&[]);
 		}
 		// make sure requests actually get processed
 		cx.flush_hard();